@@ -12,20 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use amp_common::resource::{CharacterSpec, PlaybookSpec};
 
 use iced::{Length, Subscription, Task};
-use tracing::debug;
+use tracing::{debug, error};
 
-use crate::cmd::playbook::close_playbook;
+use crate::cmd::playbook::{close_playbook, get_playbook};
 use crate::context::Context;
+use crate::errors::Result;
 use crate::styles::Theme;
-use crate::views::body::{self, Body};
+use crate::utils::progress::Reporter;
+use crate::utils::supervisor::Supervisor;
+use crate::views::body::{self, Body, TabId};
 use crate::views::cast::{self, Cast};
 use crate::views::sidebar::{self, Sidebar};
 use crate::widgets::empty::empty;
+use crate::widgets::modal::modal;
 use crate::widgets::{rule, Container, Element, Row};
 
 #[allow(clippy::large_enum_variant)]
@@ -35,11 +40,25 @@ pub enum Message {
     SidebarMessage(sidebar::Message),
     BodyMessage(body::Message),
     CastMessage(cast::Message),
+
+    /// The playbook of the selection restored on switching back to a context.
+    Restored(Selection, Result<PlaybookSpec>),
+}
+
+/// What was shown for a context, it's restored on switching back to it.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    context: String,
+    playbook: Option<String>,
+    character: Option<String>,
+    tab: TabId,
 }
 
 pub struct App {
     theme: Theme,
     ctx: Context,
+    supervisor: Supervisor,
+    reporter: Reporter,
 
     sidebar: Sidebar,
     body: Option<Body>,
@@ -47,18 +66,31 @@ pub struct App {
 
     selected_playbook: Option<Arc<PlaybookSpec>>,
     selected_character: Option<Arc<CharacterSpec>>,
+
+    /// The context of the playbook opened, which may be another cluster's.
+    viewing: Context,
+    /// The name of the context the views belong to.
+    context: String,
+    selections: HashMap<String, Selection>,
 }
 
 impl App {
     pub fn new(ctx: Context) -> (Self, Task<Message>) {
+        let supervisor = Supervisor::default();
+        let reporter = Reporter::new(None);
         let app = Self {
             theme: Theme::default(),
             ctx: ctx.clone(),
-            sidebar: Sidebar::new(ctx.clone()),
+            supervisor: supervisor.clone(),
+            reporter: reporter.clone(),
+            sidebar: Sidebar::new(ctx.clone(), supervisor, reporter),
             body: None,
             cast: None,
             selected_playbook: None,
             selected_character: None,
+            viewing: ctx.clone(),
+            context: ctx.name(),
+            selections: HashMap::new(),
         };
 
         let commands = Task::batch(vec![
@@ -77,35 +109,29 @@ impl App {
         match message {
             Message::SidebarMessage(message) => {
                 if let sidebar::Message::PlaybookSelected(result) = &message {
-                    self.body = None;
-                    self.cast = None;
-
-                    if let Some(Ok(playbook)) = result {
-                        self.selected_playbook = Some(playbook.clone().into());
-                        if let Some(characters) = &playbook.characters {
-                            debug!("characters: {:?}", characters);
-                            if characters.len() > 1 {
-                                self.cast = Some(Cast::new(self.ctx.clone(), playbook.clone()));
-                            } else {
-                                self.body = Some(Body::new(
-                                    self.ctx.clone(),
-                                    playbook.clone().into(),
-                                    characters.first().unwrap().clone().into(),
-                                ));
-                            }
-                        }
+                    let playbook = match result {
+                        Some(Ok(playbook)) => Some(playbook),
+                        _ => None,
+                    };
+                    self.open(self.ctx.clone(), playbook);
+                }
+                if let sidebar::Message::ContextPlaybookSelected(name, playbook) = &message {
+                    match self.sidebar.cluster(name) {
+                        Some(ctx) => self.open(ctx, Some(playbook)),
+                        None => error!("Failed to open the playbook of {}: it's no longer listed", name),
                     }
                 }
-                // TODO: Reset the body when the context changes.
-                // if let sidebar::Message::ContextChanged(_) = &message {
-                //     self.body = None;
-                //     self.cast = None;
-                // }
+                if let sidebar::Message::ContextChanged(Ok(())) = &message {
+                    let restore = self.change_context();
+                    let task = self.sidebar.update(message).map(Message::SidebarMessage);
+                    return Task::batch(vec![task, restore]);
+                }
                 return self.sidebar.update(message).map(Message::SidebarMessage);
             }
             Message::BodyMessage(message) => {
                 if let body::Message::CloseButtonPressed(playbook) = message {
-                    return Task::perform(close_playbook(self.ctx.clone(), playbook.id.clone()), |_| {
+                    self.supervisor.stop_playbook(&playbook.id);
+                    return Task::perform(close_playbook(self.viewing.clone(), playbook.id.clone()), |_| {
                         Message::SidebarMessage(sidebar::Message::PlaybookSelected(None))
                     });
                 }
@@ -125,9 +151,11 @@ impl App {
                     return body.update(message.clone()).map(Message::BodyMessage);
                 }
             }
+            Message::Restored(selection, result) => return self.restore(selection, result),
             Message::CastMessage(message) => {
                 if let cast::Message::CloseButtonPressed(playbook) = message {
-                    return Task::perform(close_playbook(self.ctx.clone(), playbook.id), |_| {
+                    self.supervisor.stop_playbook(&playbook.id);
+                    return Task::perform(close_playbook(self.viewing.clone(), playbook.id), |_| {
                         Message::SidebarMessage(sidebar::Message::PlaybookSelected(None))
                     });
                 }
@@ -136,7 +164,9 @@ impl App {
                     self.selected_character = Some((*character.clone()).into());
 
                     self.body = Some(Body::new(
-                        self.ctx.clone(),
+                        self.viewing.clone(),
+                        self.supervisor.clone(),
+                        self.reporter.clone(),
                         self.selected_playbook.clone().unwrap(),
                         (*character.clone()).into(),
                     ));
@@ -150,6 +180,111 @@ impl App {
         Task::none()
     }
 
+    /// Open the playbook with the context of its cluster, or close the one
+    /// opened when there is none.
+    fn open(&mut self, ctx: Context, playbook: Option<&PlaybookSpec>) {
+        self.body = None;
+        self.cast = None;
+        self.selected_playbook = None;
+        self.selected_character = None;
+        self.viewing = ctx;
+
+        let Some(playbook) = playbook else {
+            return;
+        };
+        self.selected_playbook = Some(playbook.clone().into());
+        if let Some(characters) = &playbook.characters {
+            debug!("characters: {:?}", characters);
+            if characters.len() > 1 {
+                self.cast = Some(Cast::new(self.viewing.clone(), playbook.clone()));
+            } else {
+                self.body = Some(Body::new(
+                    self.viewing.clone(),
+                    self.supervisor.clone(),
+                    self.reporter.clone(),
+                    playbook.clone().into(),
+                    characters.first().unwrap().clone().into(),
+                ));
+            }
+        }
+    }
+
+    /// Remember what was shown for the previous context and tear it down,
+    /// then restore what was shown for the current one, if anything.
+    fn change_context(&mut self) -> Task<Message> {
+        let selection = Selection {
+            context: self.context.clone(),
+            // A playbook opened from another cluster belongs to that one.
+            playbook: self
+                .selected_playbook
+                .as_ref()
+                .filter(|_| self.viewing.name() == self.context)
+                .map(|p| p.id.clone()),
+            character: self.body.as_ref().map(|b| b.character().meta.name.clone()),
+            tab: self.body.as_ref().map(|b| b.active_tab()).unwrap_or_default(),
+        };
+        self.selections.insert(selection.context.clone(), selection);
+
+        self.open(self.ctx.clone(), None);
+        self.context = self.ctx.name();
+        debug!("The views were reset for the context {}", self.context);
+
+        match self.selections.get(&self.context) {
+            Some(
+                selection @ Selection {
+                    playbook: Some(pid), ..
+                },
+            ) => {
+                let selection = selection.clone();
+                Task::perform(get_playbook(self.ctx.clone(), pid.clone()), move |result| {
+                    Message::Restored(selection.clone(), result)
+                })
+            }
+            _ => Task::none(),
+        }
+    }
+
+    fn restore(&mut self, selection: Selection, result: Result<PlaybookSpec>) -> Task<Message> {
+        // The context was switched again, or something else was selected.
+        if selection.context != self.context || self.selected_playbook.is_some() {
+            return Task::none();
+        }
+        let playbook = match result {
+            Ok(playbook) => playbook,
+            Err(e) => {
+                debug!(
+                    "The playbook of the context {} can't be restored: {}",
+                    selection.context, e
+                );
+                return Task::none();
+            }
+        };
+
+        let character = playbook
+            .characters
+            .iter()
+            .flatten()
+            .find(|c| Some(&c.meta.name) == selection.character.as_ref())
+            .cloned();
+        let mut tasks = vec![
+            self.update(Message::SidebarMessage(sidebar::Message::PlaybookSelected(Some(Ok(
+                playbook,
+            ))))),
+        ];
+        if let (Some(character), Some(_)) = (character, &self.cast) {
+            tasks.push(
+                self.update(Message::CastMessage(cast::Message::CharacterSelected(Box::new(
+                    character,
+                )))),
+            );
+        }
+        if self.body.is_some() {
+            tasks.push(self.update(Message::BodyMessage(body::Message::TabSelected(selection.tab))));
+        }
+
+        Task::batch(tasks)
+    }
+
     pub fn subscription(&self) -> Subscription<Message> {
         Subscription::batch(vec![
             self.sidebar.subscription().map(Message::SidebarMessage),
@@ -167,7 +302,7 @@ impl App {
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        Container::new(
+        let content = Container::new(
             Row::new()
                 .push(self.sidebar.view().map(Message::SidebarMessage))
                 .push(rule::vertical(1))
@@ -184,8 +319,16 @@ impl App {
                 ),
         )
         .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+        .height(Length::Fill);
+
+        match self.sidebar.modal() {
+            Some(dialog) => modal(
+                content,
+                dialog.map(Message::SidebarMessage),
+                Message::SidebarMessage(sidebar::Message::ModalClosed),
+            ),
+            None => content.into(),
+        }
     }
 
     pub fn theme(&self) -> Theme {
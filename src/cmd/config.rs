@@ -12,9 +12,74 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use amp_client::client::Client;
+use amp_common::config::{Cluster, Configuration, ContextConfiguration};
+use tracing::warn;
+
 use crate::context::Context;
-use crate::errors::Result;
+use crate::errors::{Errors, Result};
 
-pub async fn switch_context(mut ctx: Context, name: String) -> Result<()> {
+pub async fn switch_context(ctx: Context, name: String) -> Result<()> {
     ctx.switch(name).await
 }
+
+/// Add or edit the cluster of the context, once its server has been reached.
+pub async fn save_context(ctx: Context, previous: Option<String>, name: String, cluster: Cluster) -> Result<()> {
+    validate(&name, &cluster).await?;
+    ctx.save_cluster(previous.as_deref(), &name, cluster)
+}
+
+pub async fn delete_context(ctx: Context, name: String) -> Result<()> {
+    ctx.delete_cluster(&name)
+}
+
+/// Add the cluster as a new context and make it the current one, once the
+/// server has been reached with the given token.
+pub async fn add_context(name: String, cluster: Cluster) -> Result<()> {
+    validate(&name, &cluster).await?;
+
+    let path = Configuration::path().map_err(|e| Errors::InvalidConfigPath(e.to_string()))?;
+    let mut configuration = match Configuration::load(&path) {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            // Start over from a broken configuration, but keep it aside.
+            if path.exists() {
+                let backup = path.with_extension("toml.bak");
+                warn!("Failed to load the configuration, moving it to {:?}: {}", backup, err);
+                std::fs::rename(&path, &backup).map_err(|e| Errors::FailedSaveConfiguration(e.to_string()))?;
+            }
+            Configuration::default()
+        }
+    };
+
+    let context = configuration.context.get_or_insert_with(ContextConfiguration::default);
+    context
+        .add(&name, cluster)
+        .map_err(|e| Errors::FailedAddContext(e.to_string()))?;
+    context
+        .select(&name)
+        .map_err(|e| Errors::FailedSelectContext(e.to_string()))?;
+
+    configuration
+        .save(&path)
+        .map_err(|e| Errors::FailedSaveConfiguration(e.to_string()))
+}
+
+/// Check the cluster is complete and its server is reachable.
+async fn validate(name: &str, cluster: &Cluster) -> Result<()> {
+    if name.is_empty() {
+        return Err(Errors::InvalidCluster("the name is required".into()));
+    }
+    if !cluster.server.starts_with("http://") && !cluster.server.starts_with("https://") {
+        return Err(Errors::InvalidCluster("the server must be an http or https URL".into()));
+    }
+
+    let client = Client::new(&format!("{}/v1", cluster.server), cluster.token.clone());
+    client
+        .playbooks()
+        .list(None)
+        .await
+        .map_err(|e| Errors::ClientError(e.to_string()))?;
+
+    Ok(())
+}
@@ -17,7 +17,13 @@ use std::path::PathBuf;
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
-use crate::utils::{uploader, watcher};
+use crate::utils::connection_status::ConnectionStatus;
+use crate::utils::manifest::Manifest;
+use crate::utils::matcher;
+use crate::utils::progress::Reporter;
+use crate::utils::sessions::{self, Session};
+use crate::utils::supervisor::Supervisor;
+use crate::utils::uploader::{self, Preview};
 
 use amp_client::playbooks::PlaybookPayload;
 use amp_common::resource::PlaybookSpec;
@@ -25,7 +31,10 @@ use amp_common::{
     resource::{CharacterSpec, Preface},
     schema::Character,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+/// The character manifest file of a local project.
+const MANIFEST: &str = ".amp.toml";
 
 pub async fn refresh_playbooks(ctx: Context) -> Result<Vec<PlaybookSpec>> {
     ctx.client()
@@ -35,11 +44,83 @@ pub async fn refresh_playbooks(ctx: Context) -> Result<Vec<PlaybookSpec>> {
         .map_err(|e| Errors::ClientError(e.to_string()))
 }
 
+/// The playbooks of another cluster, listed along with the current one's.
+#[derive(Clone)]
+pub struct Listing {
+    /// The name of the context of the cluster.
+    pub context: String,
+    pub title: String,
+    /// The context of the cluster, kept for as long as it's listed.
+    pub ctx: Context,
+    pub status: ConnectionStatus,
+    pub playbooks: Vec<PlaybookSpec>,
+}
+
+/// List the clusters of the configuration other than the current one. The
+/// previous listings of the clusters that didn't change are kept, along
+/// with their contexts, the others get a context of their own.
+pub fn listings(ctx: &Context, mut previous: Vec<Listing>) -> Vec<Listing> {
+    let configuration = ctx.configuration();
+    let current = ctx.name();
+    let mut clusters: Vec<_> = match &configuration.context {
+        Some(context) => context
+            .clusters()
+            .iter()
+            .filter(|(name, _)| **name != current)
+            .collect(),
+        None => vec![],
+    };
+    clusters.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut listings = vec![];
+    for (name, cluster) in clusters {
+        let endpoint = (cluster.server.clone(), cluster.token.clone());
+        if let Some(index) = previous
+            .iter()
+            .position(|l| l.context == *name && l.ctx.endpoint() == endpoint)
+        {
+            let listing = previous.swap_remove(index);
+            listings.push(Listing {
+                title: cluster.title.clone(),
+                ..listing
+            });
+            continue;
+        }
+
+        match ctx.cluster(name) {
+            Ok(ctx) => listings.push(Listing {
+                context: name.clone(),
+                title: cluster.title.clone(),
+                ctx,
+                status: ConnectionStatus::default(),
+                playbooks: vec![],
+            }),
+            Err(e) => warn!("Failed to list the playbooks of {}: {}", name, e),
+        }
+    }
+    listings
+}
+
+pub async fn get_playbook(ctx: Context, pid: impl ToString) -> Result<PlaybookSpec> {
+    ctx.client()
+        .playbooks()
+        .get(&pid.to_string())
+        .await
+        .map_err(|e| Errors::ClientError(e.to_string()))
+}
+
+/// Compose a playbook from the remote git repository, or from the local
+/// character, which may be one of the characters found under the local
+/// directory, see [`scan`].
+#[allow(clippy::too_many_arguments)]
 pub async fn compose(
     ctx: Context,
+    supervisor: Supervisor,
+    reporter: Reporter,
     title: impl ToString,
     description: impl ToString,
     preface: impl ToString,
+    character: Option<PathBuf>,
     live: bool,
 ) -> Result<PlaybookSpec> {
     let playbook: PlaybookSpec;
@@ -47,10 +128,9 @@ pub async fn compose(
     if preface.to_string().starts_with("http") {
         playbook = pull(&ctx, title, description, preface).await?;
     } else {
-        let path = PathBuf::from(preface.to_string()).join(".amp.toml");
-        let workspace = path.parent().unwrap().to_path_buf();
-
-        let manifest = Character::load(path).map_err(|e| Errors::FailedLoadManifest(e.to_string()))?;
+        let workspace = character.unwrap_or_else(|| PathBuf::from(preface.to_string()));
+        let manifest =
+            Character::load(workspace.join(MANIFEST)).map_err(|e| Errors::FailedLoadManifest(e.to_string()))?;
         let actor = &manifest.meta.name;
 
         let character = CharacterSpec {
@@ -59,13 +139,61 @@ pub async fn compose(
             ..CharacterSpec::from(&manifest)
         };
 
+        // A workspace too large to upload would leave an empty playbook.
+        uploader::check(&workspace, &ctx.settings().sync).await?;
+
         playbook = load(&ctx, title, description, &character).await?;
-        sync(&ctx, playbook.clone(), actor, &workspace, live).await?;
+        if let Err(err) = sync(&ctx, &supervisor, &reporter, &playbook.id, actor, &workspace, live).await {
+            if let Err(e) = close_playbook(ctx.clone(), &playbook.id).await {
+                warn!(
+                    "Failed to close the playbook {} after its upload failed: {}",
+                    playbook.id, e
+                );
+            }
+            return Err(err);
+        }
     };
 
     Ok(playbook)
 }
 
+/// Find the characters under the given local directory, as the name and
+/// the directory of each `.amp.toml`, so that one of the services living
+/// side by side in a monorepo can be picked.
+pub async fn scan(workspace: impl Into<PathBuf>) -> Result<Vec<(String, PathBuf)>> {
+    let workspace = workspace.into();
+    tokio::task::spawn_blocking(move || {
+        let mut characters = vec![];
+        for entry in matcher::walker(&workspace).build() {
+            let entry = entry.map_err(Errors::WalkError)?;
+            if entry.file_name() != MANIFEST || !entry.path().is_file() {
+                continue;
+            }
+
+            match Character::load(entry.path()) {
+                Ok(manifest) => {
+                    let directory = entry.path().parent().unwrap_or(&workspace).to_path_buf();
+                    characters.push((manifest.meta.name.clone(), directory));
+                }
+                Err(err) => warn!("Failed to load the manifest {:?}: {}", entry.path(), err),
+            }
+        }
+        characters.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(characters)
+    })
+    .await
+    .map_err(|e| Errors::FailedScanCharacters(e.to_string()))?
+}
+
+/// Preview what composing from the given local workspace would upload.
+pub async fn preview(ctx: Context, workspace: impl Into<PathBuf>) -> Result<Preview> {
+    let workspace = workspace.into();
+    let settings = ctx.settings();
+    tokio::task::spawn_blocking(move || uploader::preview(&workspace, &settings.sync))
+        .await
+        .map_err(|e| Errors::FailedPreview(e.to_string()))?
+}
+
 /// Create a playbook from the remote git repository.
 async fn pull(
     ctx: &Context,
@@ -103,29 +231,108 @@ async fn load(
     .await
 }
 
-async fn sync(ctx: &Context, playbook: PlaybookSpec, actor: &str, workspace: &Path, live: bool) -> Result<()> {
+async fn sync(
+    ctx: &Context,
+    supervisor: &Supervisor,
+    reporter: &Reporter,
+    pid: &str,
+    actor: &str,
+    workspace: &Path,
+    live: bool,
+) -> Result<()> {
+    let settings = ctx.settings();
+
     info!("Syncing the full sources into the server...");
-    uploader::upload(ctx, &playbook.id, actor, workspace).await?;
+    uploader::upload(
+        &ctx.client(),
+        &settings.sync,
+        pid,
+        actor,
+        workspace,
+        false,
+        Some(reporter),
+    )
+    .await?;
 
     if !live {
         return Ok(());
     }
 
-    let workspace = workspace.to_path_buf();
-    let actor = actor.to_string().clone();
-    let client = ctx.client();
-    let pid1 = playbook.id.clone();
-
     info!("Watching file changes and sync the changed files.");
-    tokio::spawn(async move {
-        if let Err(err) = watcher::watch(&workspace, &client, &pid1, &actor).await {
-            error!("The watcher is stopped: {:?}", err);
-        }
-    });
+    supervisor.start(ctx.client(), settings.sync.clone(), pid, actor, workspace.to_path_buf());
+
+    // Remember the session, so that it can be resumed after a restart.
+    let session = Session {
+        pid: pid.to_string(),
+        actor: actor.to_string(),
+        workspace: workspace.to_path_buf(),
+    };
+    if let Err(err) = sessions::add(&ctx.name(), session) {
+        warn!("Failed to save the live-sync session of {}/{}: {}", pid, actor, err);
+    }
 
     Ok(())
 }
 
+/// Attach the local workspace to an actor of a running playbook, it's
+/// uploaded first and then kept in sync.
+pub async fn attach(
+    ctx: Context,
+    supervisor: Supervisor,
+    reporter: Reporter,
+    pid: String,
+    actor: String,
+    workspace: PathBuf,
+) -> Result<()> {
+    sync(&ctx, &supervisor, &reporter, &pid, &actor, &workspace, true).await
+}
+
+/// Stop syncing the local workspace of the given actor.
+pub fn detach(ctx: &Context, supervisor: &Supervisor, pid: &str, actor: &str) {
+    supervisor.stop(pid, actor);
+    if let Err(err) = sessions::remove_actor(&ctx.name(), pid, actor) {
+        warn!("Failed to forget the live-sync session of {}/{}: {}", pid, actor, err);
+    }
+}
+
+/// Get the live-sync sessions of the current context that can be resumed,
+/// the sessions of playbooks that no longer exist are forgotten.
+pub fn resumable(ctx: &Context, playbooks: &[PlaybookSpec]) -> Vec<Session> {
+    let (alive, gone): (Vec<_>, Vec<_>) = sessions::list(&ctx.name())
+        .into_iter()
+        .partition(|s| playbooks.iter().any(|p| p.id == s.pid));
+
+    for session in gone {
+        forget(ctx, &session.pid);
+    }
+
+    alive
+}
+
+/// Resume the given live-sync sessions, catching up with the changes made
+/// while the app was closed before watching again.
+pub async fn resume(ctx: Context, supervisor: Supervisor, reporter: Reporter, sessions: Vec<Session>) {
+    for Session { pid, actor, workspace } in sessions {
+        info!("Resuming the live sync of {}/{}", pid, actor);
+        if !workspace.is_dir() {
+            warn!("The workspace {:?} is gone, forgetting the session", workspace);
+            detach(&ctx, &supervisor, &pid, &actor);
+            continue;
+        }
+
+        if let Err(err) = sync(&ctx, &supervisor, &reporter, &pid, &actor, &workspace, true).await {
+            error!("Failed to resume the live sync of {}/{}: {}", pid, actor, err);
+        }
+    }
+}
+
+/// Forget the live-sync sessions of the given playbook.
+pub fn forget(ctx: &Context, pid: &str) {
+    if let Err(err) = sessions::remove(&ctx.name(), pid) {
+        warn!("Failed to forget the live-sync sessions of {}: {}", pid, err);
+    }
+}
+
 /// Create a playbook from the given payload.
 async fn create(ctx: &Context, payload: PlaybookPayload) -> Result<PlaybookSpec> {
     let playbook = ctx
@@ -142,9 +349,19 @@ async fn create(ctx: &Context, payload: PlaybookPayload) -> Result<PlaybookSpec>
 }
 
 pub async fn close_playbook(ctx: Context, pid: impl ToString) -> Result<u16> {
-    ctx.client()
+    let pid = pid.to_string();
+    let status = ctx
+        .client()
         .playbooks()
-        .delete(&pid.to_string())
+        .delete(&pid)
         .await
-        .map_err(|e| Errors::FailedDeletePlaybook(e.to_string()))
+        .map_err(|e| Errors::FailedDeletePlaybook(e.to_string()))?;
+
+    // The manifests are useless once the playbook is gone.
+    if let Err(err) = Manifest::remove(&pid) {
+        warn!("Failed to remove the manifests of {}: {}", pid, err);
+    }
+    forget(&ctx, &pid);
+
+    Ok(status)
 }
@@ -13,8 +13,9 @@
 // limitations under the License.
 
 use crate::errors::{Errors, Result};
+use crate::utils::settings::Settings;
 use amp_client::client::Client;
-use amp_common::config::{Cluster, Configuration};
+use amp_common::config::{Cluster, Configuration, ContextConfiguration};
 use std::sync::{Arc, RwLock};
 
 pub struct Context(Arc<RwLock<ContextInner>>);
@@ -22,16 +23,7 @@ pub struct Context(Arc<RwLock<ContextInner>>);
 impl Context {
     /// Initialize a new context
     pub fn init() -> Result<Context> {
-        let path = Configuration::path().map_err(|e| Errors::InvalidConfigPath(e.to_string()))?;
-        let configuration = Configuration::load(path).map_err(|e| Errors::FailedLoadConfiguration(e.to_string()))?;
-
-        let (_, cluster) = current(&configuration)?;
-        let client = Client::new(&format!("{}/v1", cluster.server), cluster.token.clone());
-
-        Ok(Context(Arc::new(RwLock::new(ContextInner {
-            configuration: Arc::new(configuration),
-            client: Arc::new(client),
-        }))))
+        Ok(Context(Arc::new(RwLock::new(ContextInner::load()?))))
     }
 
     /// Get the readonly client
@@ -44,26 +36,103 @@ impl Context {
         self.0.read().unwrap().configuration.clone()
     }
 
-    pub async fn switch(&mut self, name: String) -> Result<()> {
-        // read the configuration
-        let mut configuration = self.configuration();
-        let configuration = Arc::make_mut(&mut configuration);
-        let context = configuration.context.as_mut().unwrap();
+    /// Get the name of the current context
+    pub fn name(&self) -> String {
+        self.0.read().unwrap().name.clone()
+    }
+
+    /// Get the server and the token the client connects with
+    pub fn endpoint(&self) -> (String, Option<String>) {
+        let inner = self.0.read().unwrap();
+        (inner.server.clone(), inner.token.clone())
+    }
+
+    /// Get the readonly desktop settings of the current context
+    pub fn settings(&self) -> Arc<Settings> {
+        self.0.read().unwrap().settings.clone()
+    }
+
+    /// Get a context of another cluster of the configuration, detached from
+    /// the current one, e.g. to open its playbooks without switching.
+    pub fn cluster(&self, name: &str) -> Result<Context> {
+        let configuration = self.configuration();
+        let cluster = configuration
+            .context
+            .as_ref()
+            .and_then(|context| context.clusters().get(name).cloned())
+            .ok_or_else(|| Errors::NotFoundContext(name.to_string()))?;
+
+        let inner = ContextInner::new(configuration, name.to_string(), &cluster);
+        Ok(Context(Arc::new(RwLock::new(inner))))
+    }
+
+    pub async fn switch(&self, name: String) -> Result<()> {
+        self.modify(|context| {
+            context
+                .select(&name)
+                .map_err(|e| Errors::FailedSelectContext(e.to_string()))
+        })
+    }
+
+    /// Add a new cluster, or replace the previous one when it's edited.
+    /// Renaming the current context keeps it current.
+    pub fn save_cluster(&self, previous: Option<&str>, name: &str, cluster: Cluster) -> Result<()> {
+        self.modify(|context| {
+            if previous != Some(name) && context.clusters().contains_key(name) {
+                return Err(Errors::FailedAddContext(format!("{} already exists", name)));
+            }
 
-        // switch the context
-        context
-            .select(&name)
-            .map_err(|e| Errors::FailedSelectContext(e.to_string()))?;
+            let current = context.current().map(|(name, _)| name);
+            if let Some(previous) = previous {
+                context
+                    .delete(previous)
+                    .map_err(|e| Errors::FailedDeleteContext(e.to_string()))?;
+            }
+            context
+                .add(name, cluster)
+                .map_err(|e| Errors::FailedAddContext(e.to_string()))?;
+
+            if previous.is_some() && current.as_deref() == previous {
+                context
+                    .select(name)
+                    .map_err(|e| Errors::FailedSelectContext(e.to_string()))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Delete the cluster, except the current one.
+    pub fn delete_cluster(&self, name: &str) -> Result<()> {
+        self.modify(|context| {
+            if context.current().is_some_and(|(current, _)| current == name) {
+                return Err(Errors::FailedDeleteContext(
+                    "switch to another context before deleting the current one".into(),
+                ));
+            }
+            context
+                .delete(name)
+                .map_err(|e| Errors::FailedDeleteContext(e.to_string()))
+        })
+    }
+
+    /// Change the contexts of the configuration and save it, then reload
+    /// the shared state so that every clone sees the change.
+    fn modify(&self, f: impl FnOnce(&mut ContextConfiguration) -> Result<()>) -> Result<()> {
+        let mut configuration = (*self.configuration()).clone();
+        f(configuration.context.get_or_insert_with(ContextConfiguration::default))?;
 
-        // save the configuration
         let path = Configuration::path().map_err(|e| Errors::InvalidConfigPath(e.to_string()))?;
         configuration
             .save(path)
             .map_err(|e| Errors::FailedSaveConfiguration(e.to_string()))?;
 
-        // reload the context
-        *self = Context::init()?;
+        self.reload()
+    }
 
+    /// Reload the configuration from the disk, e.g. after it was changed by
+    /// the `amp` CLI. Every clone of the context sees the reloaded state.
+    pub fn reload(&self) -> Result<()> {
+        *self.0.write().unwrap() = ContextInner::load()?;
         Ok(())
     }
 }
@@ -78,6 +147,33 @@ impl Clone for Context {
 pub struct ContextInner {
     configuration: Arc<Configuration>,
     client: Arc<Client>,
+    settings: Arc<Settings>,
+    name: String,
+    server: String,
+    token: Option<String>,
+}
+
+impl ContextInner {
+    fn load() -> Result<Self> {
+        let path = Configuration::path().map_err(|e| Errors::InvalidConfigPath(e.to_string()))?;
+        let configuration = Configuration::load(path).map_err(|e| Errors::FailedLoadConfiguration(e.to_string()))?;
+
+        let (name, cluster) = current(&configuration)?;
+        Ok(ContextInner::new(Arc::new(configuration), name, &cluster))
+    }
+
+    fn new(configuration: Arc<Configuration>, name: String, cluster: &Cluster) -> Self {
+        let client = Client::new(&format!("{}/v1", cluster.server), cluster.token.clone());
+
+        ContextInner {
+            configuration,
+            client: Arc::new(client),
+            settings: Arc::new(Settings::load(&name)),
+            name,
+            server: cluster.server.clone(),
+            token: cluster.token.clone(),
+        }
+    }
 }
 
 /// Get the current context from the configuration
@@ -39,7 +39,6 @@ pub enum Errors {
     #[error("Failed to delete context: {0}")]
     FailedDeleteContext(String),
 
-    #[allow(dead_code)]
     #[error("Not found context: {0}")]
     NotFoundContext(String),
 
@@ -64,6 +63,9 @@ pub enum Errors {
     #[error("Walk directory error: {0}")]
     WalkError(ignore::Error),
 
+    #[error("Blocking task error: {0}")]
+    BlockingTaskError(String),
+
     #[error("Failed to strip prefix: {0}")]
     FailedStripPrefix(String),
 
@@ -87,4 +89,30 @@ pub enum Errors {
 
     #[error("Some error occurred: {0}")]
     IcedError(String),
+
+    #[error("Not found data directory")]
+    NotFoundDataDirectory,
+
+    #[error("Failed to read file: {0}")]
+    FailedReadFile(String),
+
+    #[error("Failed to write file: {0}")]
+    FailedWriteFile(String),
+
+    #[error("Failed to compress payload: {0}")]
+    FailedCompress(String),
+
+    #[error(
+        "The workspace exceeds the maximum size of {0} bytes, please add the large files to .gitignore or .ampignore"
+    )]
+    WorkspaceTooLarge(u64),
+
+    #[error("Failed to preview the upload: {0}")]
+    FailedPreview(String),
+
+    #[error("Failed to scan for characters: {0}")]
+    FailedScanCharacters(String),
+
+    #[error("Invalid cluster: {0}")]
+    InvalidCluster(String),
 }
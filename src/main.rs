@@ -15,25 +15,106 @@
 // Prevent console window from showing up on Windows
 #![windows_subsystem = "windows"]
 
-use desktop::app::{App, Message};
+use desktop::app::{self, App};
+use desktop::cmd::config::add_context;
 use desktop::context::Context;
 use desktop::errors::{Errors::IcedError, Result};
 use desktop::styles::constants::*;
 use desktop::styles::Theme;
-use iced::{window, Element, Size, Task};
+use desktop::views::onboarding::{self, Onboarding};
+use iced::{window, Element, Size, Subscription, Task};
+use tracing::error;
 use tracing_subscriber::EnvFilter;
 
-fn boot() -> (App, Task<Message>) {
-    let ctx = Context::init().expect("Failed to initialize context");
-    App::new(ctx)
+/// The desktop starts with the onboarding when there is no usable
+/// configuration, instead of giving up before any window appears.
+#[allow(clippy::large_enum_variant)]
+enum Desktop {
+    Onboarding(Onboarding),
+    Ready(App),
 }
 
-fn update(app: &mut App, message: Message) -> Task<Message> {
-    app.update(message)
+#[derive(Clone, Debug)]
+enum Message {
+    Onboarding(onboarding::Message),
+    App(app::Message),
 }
 
-fn view(app: &App) -> Element<'_, Message, Theme> {
-    app.view()
+fn boot() -> (Desktop, Task<Message>) {
+    match Context::init() {
+        Ok(ctx) => ready(ctx),
+        Err(err) => {
+            error!("Failed to initialize context: {}", err);
+            (Desktop::Onboarding(Onboarding::new(err)), Task::none())
+        }
+    }
+}
+
+fn ready(ctx: Context) -> (Desktop, Task<Message>) {
+    let (app, task) = App::new(ctx);
+    (Desktop::Ready(app), task.map(Message::App))
+}
+
+fn update(desktop: &mut Desktop, message: Message) -> Task<Message> {
+    match message {
+        Message::App(message) => {
+            if let Desktop::Ready(app) = desktop {
+                return app.update(message).map(Message::App);
+            }
+        }
+        Message::Onboarding(message) => {
+            let Desktop::Onboarding(onboarding) = desktop else {
+                return Task::none();
+            };
+
+            match onboarding.update(message) {
+                onboarding::Action::None => {}
+                onboarding::Action::Submit(name, cluster) => {
+                    return Task::perform(add_context(name, cluster), |result| {
+                        Message::Onboarding(onboarding::Message::Saved(result))
+                    });
+                }
+                onboarding::Action::Ready => match Context::init() {
+                    Ok(ctx) => {
+                        let (app, task) = ready(ctx);
+                        *desktop = app;
+                        return task;
+                    }
+                    Err(err) => onboarding.failed(err),
+                },
+            }
+        }
+    }
+
+    Task::none()
+}
+
+fn view(desktop: &Desktop) -> Element<'_, Message, Theme> {
+    match desktop {
+        Desktop::Onboarding(onboarding) => onboarding.view().map(Message::Onboarding),
+        Desktop::Ready(app) => app.view().map(Message::App),
+    }
+}
+
+fn title(desktop: &Desktop) -> String {
+    match desktop {
+        Desktop::Onboarding(_) => String::from("Amphitheatre Desktop"),
+        Desktop::Ready(app) => app.title(),
+    }
+}
+
+fn subscription(desktop: &Desktop) -> Subscription<Message> {
+    match desktop {
+        Desktop::Onboarding(_) => Subscription::none(),
+        Desktop::Ready(app) => app.subscription().map(Message::App),
+    }
+}
+
+fn theme(desktop: &Desktop) -> Theme {
+    match desktop {
+        Desktop::Onboarding(_) => Theme::default(),
+        Desktop::Ready(app) => app.theme(),
+    }
 }
 
 fn main() -> Result<()> {
@@ -46,15 +127,15 @@ fn main() -> Result<()> {
         .init();
 
     iced::application(boot, update, view)
-        .title(App::title)
+        .title(title)
         .window(window::Settings {
             size: Size::new(WINDOW_INITIAL_WIDTH, WINDOW_INITIAL_HEIGHT),
             min_size: Some(Size::new(WINDOW_INITIAL_WIDTH, WINDOW_INITIAL_HEIGHT)),
             ..window::Settings::default()
         })
-        .subscription(App::subscription)
+        .subscription(subscription)
         .font(iced_fonts::BOOTSTRAP_FONT_BYTES)
-        .theme(App::theme)
+        .theme(theme)
         .centered()
         .run()
         .map_err(|e| IcedError(e.to_string()))
@@ -53,3 +53,10 @@ pub fn toolbar(theme: &Theme) -> Style {
         ..Style::default()
     }
 }
+
+pub fn backdrop(_theme: &Theme) -> Style {
+    Style {
+        background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.6).into()),
+        ..Style::default()
+    }
+}
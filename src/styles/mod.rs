@@ -27,6 +27,8 @@ pub mod checkbox;
 pub mod constants;
 pub mod container;
 pub mod menu;
+pub mod progress_bar;
+pub mod radio;
 pub mod rule;
 pub mod scrollable;
 pub mod tab_bar;
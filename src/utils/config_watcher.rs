@@ -0,0 +1,58 @@
+// Copyright 2024 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures::Stream;
+use notify::EventKind::Access;
+use notify::RecursiveMode::NonRecursive;
+use notify::{Event, RecommendedWatcher, Watcher};
+use tokio::sync::mpsc;
+
+use crate::errors::{Errors, Result};
+
+/// How long the writes to the file are given to settle down.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Watch the configuration file for changes made outside of the desktop,
+/// e.g. by `amp context use`. A burst of writes yields one change.
+pub fn watch(path: PathBuf) -> Result<impl Stream<Item = ()>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    // The file is often replaced rather than written in place, so the
+    // directory is watched and the events of the file picked out.
+    let name = path.file_name().map(|name| name.to_os_string());
+    let handler = move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            let touched = event.paths.iter().any(|p| p.file_name() == name.as_deref());
+            if touched && !matches!(event.kind, Access(_)) {
+                let _ = tx.send(());
+            }
+        }
+    };
+    let config = notify::Config::default();
+    let mut watcher =
+        RecommendedWatcher::new(handler, config).map_err(|e| Errors::FailedCreateWatcher(e.to_string()))?;
+    watcher
+        .watch(path.parent().unwrap_or(Path::new(".")), NonRecursive)
+        .map_err(|e| Errors::FailedWatchDirectory(e.to_string()))?;
+
+    Ok(futures::stream::unfold((watcher, rx), |(watcher, mut rx)| async move {
+        rx.recv().await?;
+        tokio::time::sleep(SETTLE).await;
+        while rx.try_recv().is_ok() {}
+        Some(((), (watcher, rx)))
+    }))
+}
@@ -13,13 +13,41 @@
 // limitations under the License.
 
 use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use amp_client::client::Client;
+use futures::{Stream, StreamExt};
+use tokio::time::{sleep, timeout};
+
+/// How often the cluster is probed while connected.
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+/// How long a probe may take before the cluster is considered unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// The delay before the first retry, doubled on every failure up to the max.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Debug, Default)]
 pub enum ConnectionStatus {
     #[default]
     Connecting,
-    Connected,
-    Disconnected,
+    /// The last probe succeeded, with its round-trip time.
+    Connected(Duration),
+    /// The last probe failed, it's retried after the delay.
+    Disconnected { error: String, retry: Duration },
+    /// Probing again, after the given number of failures.
+    Reconnecting(u32),
+}
+
+impl ConnectionStatus {
+    /// The error of the last probe, while disconnected.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            ConnectionStatus::Disconnected { error, .. } => Some(error),
+            _ => None,
+        }
+    }
 }
 
 // impl std::fmt::Display for State
@@ -27,8 +55,67 @@ impl Display for ConnectionStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConnectionStatus::Connecting => write!(f, "Connecting..."),
-            ConnectionStatus::Connected => write!(f, "Connected"),
-            ConnectionStatus::Disconnected => write!(f, "Disconnected. Retrying..."),
+            ConnectionStatus::Connected(latency) => write!(f, "Connected ({} ms)", latency.as_millis()),
+            ConnectionStatus::Disconnected { retry, .. } => {
+                write!(f, "Disconnected. Retrying in {}s", retry.as_secs())
+            }
+            ConnectionStatus::Reconnecting(attempt) => write!(f, "Reconnecting (attempt {})...", attempt),
         }
     }
 }
+
+/// Monitor the connection to the cluster of the client, by probing it
+/// periodically, and backing off exponentially while it's unreachable.
+pub fn monitor(client: Arc<Client>) -> impl Stream<Item = ConnectionStatus> {
+    let monitor = Monitor {
+        client,
+        failures: 0,
+        wait: None,
+    };
+
+    let probes = futures::stream::unfold(monitor, |mut monitor| async move {
+        if let Some(wait) = monitor.wait.take() {
+            sleep(wait).await;
+            if monitor.failures > 0 {
+                return Some((ConnectionStatus::Reconnecting(monitor.failures), monitor));
+            }
+        }
+
+        let status = monitor.probe().await;
+        monitor.wait = Some(match &status {
+            ConnectionStatus::Disconnected { retry, .. } => *retry,
+            _ => PROBE_INTERVAL,
+        });
+        Some((status, monitor))
+    });
+
+    futures::stream::once(async { ConnectionStatus::Connecting }).chain(probes)
+}
+
+struct Monitor {
+    client: Arc<Client>,
+    /// The number of failed probes in a row.
+    failures: u32,
+    /// How long to wait before the next probe.
+    wait: Option<Duration>,
+}
+
+impl Monitor {
+    async fn probe(&mut self) -> ConnectionStatus {
+        let start = Instant::now();
+        let error = match timeout(PROBE_TIMEOUT, self.client.playbooks().list(None)).await {
+            Ok(Ok(_)) => {
+                self.failures = 0;
+                return ConnectionStatus::Connected(start.elapsed());
+            }
+            Ok(Err(err)) => err.to_string(),
+            Err(_) => format!("no response in {}s", PROBE_TIMEOUT.as_secs()),
+        };
+
+        let retry = MIN_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(MAX_BACKOFF);
+        self.failures += 1;
+        ConnectionStatus::Disconnected { error, retry }
+    }
+}
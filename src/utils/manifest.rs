@@ -0,0 +1,282 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::errors::{Errors, Result};
+use crate::utils::{storage, uploader};
+
+/// The hash recorded for a directory.
+const DIRECTORY: &str = "directory";
+
+/// A file as it was uploaded last time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    /// The content hash of a file, or what a directory or symlink is.
+    hash: String,
+    size: u64,
+    modified: u128,
+    #[serde(default)]
+    mode: u32,
+}
+
+impl Entry {
+    /// Read the entry of the file at the given path, the hash of the
+    /// previous entry is reused when the size and modification time match.
+    fn read(path: &Path, previous: Option<&Entry>) -> Result<Self> {
+        let metadata = fs::symlink_metadata(path).map_err(|e| Errors::FailedReadFile(e.to_string()))?;
+        let size = metadata.len();
+        let mode = uploader::mode(path, &metadata);
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+
+        let unchanged = previous.filter(|entry| entry.size == size && entry.modified == modified);
+        let hash = match unchanged {
+            Some(entry) => entry.hash.clone(),
+            None if metadata.is_dir() => String::from(DIRECTORY),
+            None if metadata.is_symlink() => {
+                let target = fs::read_link(path).map_err(|e| Errors::FailedReadFile(e.to_string()))?;
+                format!("symlink:{}", target.display())
+            }
+            None => hash(path)?,
+        };
+
+        Ok(Entry {
+            hash,
+            size,
+            modified,
+            mode,
+        })
+    }
+}
+
+/// Manifest records the content hash of every file uploaded for an actor,
+/// so that later uploads only need to send what has changed since.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    files: BTreeMap<PathBuf, Entry>,
+}
+
+/// The difference between two manifests.
+#[derive(Debug, Default)]
+pub struct Diff {
+    /// The files added or changed, their modes included, by their relative paths.
+    pub changed: BTreeSet<PathBuf>,
+    /// The files removed, by their relative paths.
+    pub removed: BTreeSet<PathBuf>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// The removed paths to request, leaving out those below a removed
+    /// directory, as they are gone with it.
+    pub fn removals(&self) -> impl Iterator<Item = &PathBuf> {
+        self.removed
+            .iter()
+            .filter(|name| !name.ancestors().skip(1).any(|p| self.removed.contains(p)))
+    }
+}
+
+impl Manifest {
+    /// Build the manifest of the given `(path, name)` pairs.
+    ///
+    /// The hash of a file whose size and modification time match the
+    /// previous manifest is reused rather than computed again.
+    pub fn build(paths: &[(PathBuf, PathBuf)], previous: Option<&Manifest>) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for (path, name) in paths {
+            let previous = previous.and_then(|manifest| manifest.files.get(name));
+            files.insert(name.clone(), Entry::read(path, previous)?);
+        }
+
+        Ok(Manifest { files })
+    }
+
+    /// Record the file at the given path as it is now, or forget it and
+    /// everything below it if it's gone.
+    pub fn record(&mut self, name: &Path, path: &Path) -> Result<()> {
+        if fs::symlink_metadata(path).is_err() {
+            self.files.retain(|n, _| !n.starts_with(name));
+            return Ok(());
+        }
+
+        let entry = Entry::read(path, self.files.get(name))?;
+        self.files.insert(name.to_path_buf(), entry);
+        Ok(())
+    }
+
+    /// Compare this manifest with a newer one.
+    pub fn diff(&self, current: &Manifest) -> Diff {
+        let changed = current
+            .files
+            .iter()
+            .filter(|(name, entry)| {
+                self.files
+                    .get(*name)
+                    .is_none_or(|e| e.hash != entry.hash || e.mode != entry.mode)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        let removed = self
+            .files
+            .keys()
+            .filter(|name| !current.files.contains_key(*name))
+            .cloned()
+            .collect();
+
+        Diff { changed, removed }
+    }
+
+    /// Whether the given path was a directory.
+    pub fn is_dir(&self, name: &Path) -> bool {
+        self.files.get(name).is_some_and(|entry| entry.hash == DIRECTORY)
+    }
+
+    /// Load the manifest of the given actor, `None` if there is no usable one.
+    pub fn load(pid: &str, actor: &str) -> Option<Self> {
+        let manifest = location(pid, actor).and_then(|path| storage::load(&path));
+        match manifest {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                warn!("Failed to load the manifest of {}/{}: {}", pid, actor, err);
+                None
+            }
+        }
+    }
+
+    /// Save the manifest for the given actor.
+    pub fn save(&self, pid: &str, actor: &str) -> Result<()> {
+        storage::save(&location(pid, actor)?, self)
+    }
+
+    /// Delete the manifest of the given actor, so that its next upload
+    /// sends the entire workspace.
+    pub fn delete(pid: &str, actor: &str) -> Result<()> {
+        let path = location(pid, actor)?;
+        if path.exists() {
+            fs::remove_file(path).map_err(|e| Errors::FailedWriteFile(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Remove the manifests of all actors of the given playbook.
+    pub fn remove(pid: &str) -> Result<()> {
+        let dir = storage::data_dir()?.join("manifests").join(pid);
+        if dir.exists() {
+            debug!("Removing the manifests in {:?}", dir);
+            fs::remove_dir_all(dir).map_err(|e| Errors::FailedWriteFile(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+fn location(pid: &str, actor: &str) -> Result<PathBuf> {
+    Ok(storage::data_dir()?
+        .join("manifests")
+        .join(pid)
+        .join(format!("{actor}.json")))
+}
+
+/// Compute the SHA-256 hash of the given file.
+fn hash(path: &Path) -> Result<String> {
+    let mut file = File::open(path).map_err(|e| Errors::FailedReadFile(e.to_string()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| Errors::FailedReadFile(e.to_string()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(files: &[(&str, &str)]) -> Manifest {
+        let files = files
+            .iter()
+            .map(|(name, hash)| {
+                let entry = Entry {
+                    hash: hash.to_string(),
+                    size: 0,
+                    modified: 0,
+                    mode: 0o644,
+                };
+                (PathBuf::from(name), entry)
+            })
+            .collect();
+        Manifest { files }
+    }
+
+    fn paths(names: &[&str]) -> BTreeSet<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn diff_changed_and_removed() {
+        let previous = manifest(&[("a.rs", "1"), ("b.rs", "2"), ("c.rs", "3")]);
+        let current = manifest(&[("a.rs", "1"), ("b.rs", "4"), ("d.rs", "5")]);
+
+        let diff = previous.diff(&current);
+        assert_eq!(diff.changed, paths(&["b.rs", "d.rs"]));
+        assert_eq!(diff.removed, paths(&["c.rs"]));
+        assert!(previous.diff(&previous).is_empty());
+    }
+
+    #[test]
+    fn diff_renamed() {
+        let previous = manifest(&[("old.rs", "1")]);
+        let current = manifest(&[("new.rs", "1")]);
+
+        let diff = previous.diff(&current);
+        assert_eq!(diff.changed, paths(&["new.rs"]));
+        assert_eq!(diff.removed, paths(&["old.rs"]));
+    }
+
+    #[test]
+    fn removals_inside_surviving_directory() {
+        let previous = manifest(&[("src", DIRECTORY), ("src/main.rs", "1"), ("src/old.rs", "2")]);
+        let current = manifest(&[("src", DIRECTORY), ("src/main.rs", "1")]);
+
+        let diff = previous.diff(&current);
+        let removals: Vec<_> = diff.removals().cloned().collect();
+        assert_eq!(removals, vec![PathBuf::from("src/old.rs")]);
+    }
+
+    #[test]
+    fn removals_of_removed_directory() {
+        let previous = manifest(&[
+            ("a.rs", "1"),
+            ("old", DIRECTORY),
+            ("old/b.rs", "2"),
+            ("old/c", DIRECTORY),
+        ]);
+        let current = manifest(&[("a.rs", "1")]);
+
+        let diff = previous.diff(&current);
+        let removals: Vec<_> = diff.removals().cloned().collect();
+        assert_eq!(removals, vec![PathBuf::from("old")]);
+    }
+}
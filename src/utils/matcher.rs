@@ -0,0 +1,193 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::{Match, WalkBuilder};
+use tracing::{debug, warn};
+
+/// The project-specific ignore file, it takes precedence over `.gitignore`.
+pub const AMPIGNORE: &str = ".ampignore";
+const GITIGNORE: &str = ".gitignore";
+/// The repository internals are never synced.
+const GIT_DIR: &str = ".git";
+/// The rule reported for hidden files.
+const HIDDEN: &str = "hidden files are not synced";
+
+/// Build a walker over the given directory, following the same rules as
+/// the [`Matcher`]: the `.gitignore` and `.ampignore` files of the directory,
+/// of the ones below it and of the ones above it, the git excludes, and
+/// `.git` itself.
+///
+/// Hidden files such as `.env` are not synced, as they often hold local
+/// secrets, unless an ignore file whitelists them, e.g. `!.babelrc`.
+pub fn walker(path: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .hidden(true)
+        .ignore(false)
+        .parents(true)
+        .require_git(false)
+        .add_custom_ignore_filename(AMPIGNORE)
+        .filter_entry(|entry| entry.file_name() != GIT_DIR);
+    builder
+}
+
+/// Matcher tells whether a single path of the workspace is ignored, for
+/// the paths reported by the watcher.
+pub struct Matcher {
+    root: PathBuf,
+    /// The ignore files of each directory, loaded on demand.
+    dirs: HashMap<PathBuf, Vec<Gitignore>>,
+    /// The global excludes and `.git/info/exclude`.
+    excludes: Vec<Gitignore>,
+}
+
+impl Matcher {
+    pub fn new(root: &Path) -> Self {
+        let mut excludes = vec![];
+        excludes.extend(build(root, &root.join(GIT_DIR).join("info").join("exclude")));
+
+        let (global, err) = GitignoreBuilder::new(root).build_global();
+        if let Some(err) = err {
+            warn!("Failed to load the global git excludes: {}", err);
+        }
+        excludes.push(global);
+
+        Self {
+            root: root.to_path_buf(),
+            dirs: HashMap::new(),
+            excludes,
+        }
+    }
+
+    /// Check whether the given path is ignored.
+    pub fn is_ignored(&mut self, path: &Path) -> bool {
+        match self.explain(path) {
+            Some(rule) => {
+                debug!("The file is ignored by {}: {:?}", rule, path);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Describe the rule that ignores the given path, `None` if it's not ignored.
+    pub fn explain(&mut self, path: &Path) -> Option<String> {
+        let Ok(name) = path.strip_prefix(&self.root) else {
+            return Some(String::from("outside of the workspace"));
+        };
+        if name.components().any(|c| c.as_os_str() == GIT_DIR) {
+            return Some(String::from(".git is never synced"));
+        }
+
+        // Like the walker, skip everything below a hidden directory unless
+        // the directory itself is whitelisted.
+        let mut dir = self.root.clone();
+        for component in name.parent().into_iter().flat_map(|parent| parent.components()) {
+            dir.push(component);
+            if is_hidden(&dir) && !self.rule(&dir, true).is_whitelist() {
+                return Some(String::from(HIDDEN));
+            }
+        }
+
+        match self.rule(path, path.is_dir()) {
+            Match::Ignore(rule) => Some(rule),
+            Match::Whitelist(_) => None,
+            Match::None if is_hidden(path) => Some(String::from(HIDDEN)),
+            Match::None => None,
+        }
+    }
+
+    /// Forget the rules of the directory when the given path is one of its
+    /// ignore files, so that the changed rules are loaded next time.
+    pub fn reload(&mut self, path: &Path) {
+        let is_ignore_file = path
+            .file_name()
+            .is_some_and(|name| name == AMPIGNORE || name == GITIGNORE);
+        if let Some(dir) = path.parent().filter(|_| is_ignore_file) {
+            self.dirs.remove(dir);
+        }
+    }
+
+    /// Find the rule matching the given path, described for the user.
+    fn rule(&mut self, path: &Path, is_dir: bool) -> Match<String> {
+        let root = self.root.clone();
+
+        // The rules of a deeper directory override the ones above it, and
+        // within a directory `.ampignore` overrides `.gitignore`. The ignore
+        // files above the workspace apply as well, e.g. the root `.gitignore`
+        // of a monorepo.
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            for ignore in self.load(current) {
+                match ignore.matched_path_or_any_parents(path, is_dir) {
+                    Match::None => continue,
+                    Match::Whitelist(glob) => return Match::Whitelist(describe(&root, glob)),
+                    Match::Ignore(glob) => return Match::Ignore(describe(&root, glob)),
+                }
+            }
+            dir = current.parent();
+        }
+
+        self.excludes
+            .iter()
+            .map(|ignore| ignore.matched_path_or_any_parents(path, is_dir))
+            .find(|matched| !matched.is_none())
+            .map(|matched| matched.map(|glob| describe(&root, glob)))
+            .unwrap_or(Match::None)
+    }
+
+    fn load(&mut self, dir: &Path) -> &[Gitignore] {
+        self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+            [AMPIGNORE, GITIGNORE]
+                .iter()
+                .filter_map(|name| build(dir, &dir.join(name)))
+                .collect()
+        })
+    }
+}
+
+/// Check whether the given path is hidden, i.e. its name starts with a dot.
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Describe the rule with the ignore file it comes from, e.g. `node_modules/ (.gitignore)`.
+fn describe(root: &Path, glob: &Glob) -> String {
+    let source = match glob.from() {
+        Some(file) => file.strip_prefix(root).unwrap_or(file).display().to_string(),
+        None => String::from("global excludes"),
+    };
+    format!("{} ({})", glob.original(), source)
+}
+
+/// Build the rules of a single ignore file, if it exists.
+fn build(root: &Path, file: &Path) -> Option<Gitignore> {
+    if !file.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(err) = builder.add(file) {
+        warn!("Failed to read the ignore file {:?}: {}", file, err);
+    }
+    builder
+        .build()
+        .map_err(|err| warn!("Failed to parse the ignore file {:?}: {}", file, err))
+        .ok()
+}
@@ -12,7 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod config_watcher;
 pub mod connection_status;
+pub mod manifest;
+pub mod matcher;
+pub mod playbook_watcher;
+pub mod progress;
+pub mod sessions;
+pub mod settings;
+pub mod storage;
 pub mod strings;
+pub mod supervisor;
 pub mod uploader;
 pub mod watcher;
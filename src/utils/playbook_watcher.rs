@@ -0,0 +1,315 @@
+// Copyright 2024 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use amp_client::client::Client;
+use amp_common::resource::PlaybookSpec;
+use futures::stream::{AbortHandle, Abortable, BoxStream, SelectAll};
+use futures::{Stream, StreamExt};
+use reqwest_eventsource::Event;
+use tokio::time::{sleep, sleep_until, Instant};
+use tracing::{debug, warn};
+
+use crate::errors::Errors;
+
+/// The bounds of the adaptive polling of the list, which finds the
+/// playbooks created or deleted elsewhere and refreshes the ones that can't
+/// be streamed. It slows down while nothing changes, and speeds up on any
+/// change.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The bounds of the delay before subscribing again to the events of a
+/// playbook whose stream failed or ended, doubled on each failure in a row.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// The failures in a row after which a playbook is polled instead.
+const MAX_STREAM_FAILURES: u32 = 5;
+/// How long a stream has to stay open before its earlier failures are
+/// forgiven, so that one closing right after it opens still falls back.
+const STABLE_STREAM: Duration = Duration::from_secs(30);
+/// How long the events are collected before the changed playbooks are
+/// fetched, so that a burst of events costs a single fetch per playbook.
+const COALESCE_DELAY: Duration = Duration::from_millis(500);
+
+/// A change of the playbooks of a cluster.
+#[derive(Clone, Debug)]
+pub enum PlaybookEvent {
+    /// The playbook was created, or its changes are not known.
+    Added(PlaybookSpec),
+    Updated(PlaybookSpec),
+    Removed(String),
+    /// All the playbooks of the cluster were listed once.
+    Ready,
+    Failed(Errors),
+}
+
+/// Watch the playbooks of the cluster of the client, the changes of the
+/// known playbooks are streamed from their events, and the list is polled
+/// to find the created and removed ones. The playbooks whose events can't
+/// be streamed are refreshed by the polling instead.
+pub fn watch(client: Arc<Client>) -> impl Stream<Item = PlaybookEvent> {
+    let watcher = Watcher {
+        client,
+        known: HashMap::new(),
+        signals: SelectAll::new(),
+        queue: VecDeque::new(),
+        changed: HashSet::new(),
+        flush: None,
+        ready: false,
+        interval: MIN_POLL_INTERVAL,
+        deadline: Instant::now(),
+    };
+
+    futures::stream::unfold(watcher, |mut watcher| async move {
+        let event = watcher.next().await;
+        Some((event, watcher))
+    })
+}
+
+/// What happened to the event stream of a playbook.
+enum Signal {
+    Opened,
+    Changed,
+    Failed,
+    Ended,
+    /// The delay before subscribing again has passed.
+    Retry,
+}
+
+/// The event stream of a known playbook.
+#[derive(Default)]
+struct Feed {
+    /// The handle to stop the stream, or the pending retry.
+    handle: Option<AbortHandle>,
+    /// When the stream was opened last.
+    opened: Option<Instant>,
+    /// The failures of the stream in a row.
+    failures: u32,
+    /// Whether the playbook is polled, as its events can't be streamed.
+    polling: bool,
+}
+
+impl Feed {
+    fn abort(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+struct Watcher {
+    client: Arc<Client>,
+    /// The known playbooks, with their event streams.
+    known: HashMap<String, Feed>,
+    /// The signals of the event streams and the retries, by playbook id.
+    signals: SelectAll<BoxStream<'static, (String, Signal)>>,
+    queue: VecDeque<PlaybookEvent>,
+    /// The playbooks which have new events, fetched once the events settle.
+    changed: HashSet<String>,
+    flush: Option<Instant>,
+    ready: bool,
+    interval: Duration,
+    deadline: Instant,
+}
+
+impl Watcher {
+    async fn next(&mut self) -> PlaybookEvent {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return event;
+            }
+
+            let flush = self.flush.unwrap_or(self.deadline);
+            tokio::select! {
+                Some((pid, signal)) = self.signals.next(), if !self.signals.is_empty() => {
+                    self.handle(pid, signal);
+                }
+                _ = sleep_until(flush), if self.flush.is_some() => self.refresh().await,
+                _ = sleep_until(self.deadline) => self.poll().await,
+            }
+        }
+    }
+
+    fn handle(&mut self, pid: String, signal: Signal) {
+        let Some(feed) = self.known.get_mut(&pid) else {
+            return;
+        };
+        match signal {
+            Signal::Opened => feed.opened = Some(Instant::now()),
+            Signal::Changed => {
+                self.changed.insert(pid);
+                self.flush.get_or_insert_with(|| Instant::now() + COALESCE_DELAY);
+            }
+            Signal::Failed | Signal::Ended => {
+                feed.abort();
+                if feed.opened.take().is_some_and(|at| at.elapsed() >= STABLE_STREAM) {
+                    feed.failures = 0;
+                }
+                feed.failures += 1;
+                if feed.failures >= MAX_STREAM_FAILURES {
+                    self.fall_back(&pid);
+                    return;
+                }
+
+                let delay = MIN_RETRY_DELAY
+                    .saturating_mul(1 << (feed.failures - 1))
+                    .min(MAX_RETRY_DELAY);
+                debug!("The events of the playbook {} stopped, retrying in {:?}", pid, delay);
+                let handle = self.retry(&pid, delay);
+                if let Some(feed) = self.known.get_mut(&pid) {
+                    feed.handle = Some(handle);
+                }
+            }
+            Signal::Retry => {
+                let handle = self.subscribe(&pid);
+                if let Some(feed) = self.known.get_mut(&pid) {
+                    feed.handle = Some(handle);
+                }
+            }
+        }
+    }
+
+    /// Fetch the playbooks which have new events.
+    async fn refresh(&mut self) {
+        self.flush = None;
+        for pid in std::mem::take(&mut self.changed) {
+            match self.client.playbooks().get(&pid).await {
+                Ok(playbook) => self.queue.push_back(PlaybookEvent::Updated(playbook)),
+                Err(e) => {
+                    // It may have been deleted, let the list tell.
+                    debug!("Failed to get the playbook {}: {}", pid, e);
+                    self.deadline = Instant::now();
+                }
+            }
+        }
+    }
+
+    /// Stop streaming the events of the playbook, and poll it instead.
+    fn fall_back(&mut self, pid: &str) {
+        warn!("The events of the playbook {} can't be streamed, polling instead", pid);
+        if let Some(feed) = self.known.get_mut(pid) {
+            feed.abort();
+            feed.polling = true;
+        }
+        self.interval = MIN_POLL_INTERVAL;
+        self.deadline = Instant::now();
+    }
+
+    /// List the playbooks, and queue the differences with the known ones.
+    async fn poll(&mut self) {
+        let playbooks = match self.client.playbooks().list(None).await {
+            Ok(playbooks) => playbooks,
+            Err(e) => {
+                self.queue
+                    .push_back(PlaybookEvent::Failed(Errors::ClientError(e.to_string())));
+                self.interval = (self.interval * 2).clamp(MIN_POLL_INTERVAL, MAX_RETRY_DELAY);
+                self.deadline = Instant::now() + self.interval;
+                return;
+            }
+        };
+
+        let ids: HashSet<_> = playbooks.iter().map(|p| p.id.clone()).collect();
+        let removed: Vec<_> = self.known.keys().filter(|id| !ids.contains(*id)).cloned().collect();
+        let changed = !removed.is_empty() || playbooks.iter().any(|p| !self.known.contains_key(&p.id));
+
+        for pid in removed {
+            if let Some(mut feed) = self.known.remove(&pid) {
+                feed.abort();
+            }
+            self.changed.remove(&pid);
+            self.queue.push_back(PlaybookEvent::Removed(pid));
+        }
+        for playbook in playbooks {
+            match self.known.get(&playbook.id) {
+                None => {
+                    let feed = Feed {
+                        handle: Some(self.subscribe(&playbook.id)),
+                        ..Default::default()
+                    };
+                    self.known.insert(playbook.id.clone(), feed);
+                    self.queue.push_back(PlaybookEvent::Added(playbook));
+                }
+                // Nothing tells what changed, so it's updated on every poll.
+                Some(feed) if feed.polling => {
+                    self.queue.push_back(PlaybookEvent::Updated(playbook));
+                }
+                Some(_) => {}
+            }
+        }
+
+        if !self.ready {
+            self.ready = true;
+            self.queue.push_back(PlaybookEvent::Ready);
+        }
+        self.schedule(changed);
+    }
+
+    /// Stream the events of the playbook as signals, it ends with
+    /// [`Signal::Ended`] when the server closes the stream.
+    fn subscribe(&mut self, pid: &str) -> AbortHandle {
+        let id = pid.to_string();
+        let events = self.client.playbooks().events(pid).filter_map(move |event| {
+            let signal = match event {
+                Ok(Event::Open) => Signal::Opened,
+                Ok(Event::Message(_)) => Signal::Changed,
+                Err(_) => Signal::Failed,
+            };
+            futures::future::ready(Some((id.clone(), signal)))
+        });
+        let ended = futures::stream::once(futures::future::ready((pid.to_string(), Signal::Ended)));
+
+        self.push(events.chain(ended))
+    }
+
+    /// Signal to subscribe again to the events of the playbook after the delay.
+    fn retry(&mut self, pid: &str, delay: Duration) -> AbortHandle {
+        let id = pid.to_string();
+        self.push(futures::stream::once(async move {
+            sleep(delay).await;
+            (id, Signal::Retry)
+        }))
+    }
+
+    fn push(&mut self, signals: impl Stream<Item = (String, Signal)> + Send + 'static) -> AbortHandle {
+        let (handle, registration) = AbortHandle::new_pair();
+        self.signals.push(Abortable::new(signals, registration).boxed());
+        handle
+    }
+
+    fn schedule(&mut self, changed: bool) {
+        self.interval = match changed {
+            true => MIN_POLL_INTERVAL,
+            false => (self.interval * 2).clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL),
+        };
+        self.deadline = Instant::now() + self.interval;
+    }
+}
+
+/// Apply the change to the playbooks, the added ones replace the known
+/// ones by the same id.
+pub fn apply(playbooks: &mut Vec<PlaybookSpec>, event: PlaybookEvent) {
+    match event {
+        PlaybookEvent::Added(playbook) | PlaybookEvent::Updated(playbook) => {
+            match playbooks.iter_mut().find(|p| p.id == playbook.id) {
+                Some(existing) => *existing = playbook,
+                None => playbooks.push(playbook),
+            }
+        }
+        PlaybookEvent::Removed(pid) => playbooks.retain(|p| p.id != pid),
+        PlaybookEvent::Ready | PlaybookEvent::Failed(_) => {}
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::utils::strings::format_bytes;
+
+/// Reporter publishes the progress of an upload, `None` while idle.
+pub type Reporter = tokio::sync::watch::Sender<Option<Progress>>;
+
+/// The progress of an upload.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub actor: String,
+    pub files: usize,
+    pub total_files: usize,
+    pub bytes: u64,
+    pub total_bytes: u64,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(actor: impl ToString, total_files: usize, total_bytes: u64) -> Self {
+        Self {
+            actor: actor.to_string(),
+            files: 0,
+            total_files,
+            bytes: 0,
+            total_bytes,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn advance(&mut self, files: usize, bytes: u64) {
+        self.files += files;
+        self.bytes += bytes;
+    }
+
+    /// The completed part of the upload, between 0 and 1.
+    pub fn ratio(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        self.bytes as f32 / self.total_bytes as f32
+    }
+
+    /// The estimated time left, based on the throughput so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.bytes == 0 {
+            return None;
+        }
+        let left = self.total_bytes.saturating_sub(self.bytes) as f64 / self.bytes as f64;
+        Some(self.started.elapsed().mul_f64(left))
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} files, {} / {}",
+            self.files,
+            self.total_files,
+            format_bytes(self.bytes),
+            format_bytes(self.total_bytes)
+        )?;
+        if let Some(eta) = self.eta() {
+            write!(f, ", {}s left", eta.as_secs())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::errors::Result;
+use crate::utils::storage;
+
+/// A live-sync session, an actor linked to a local workspace.
+///
+/// The sessions are kept for each context, so that they can be resumed
+/// after the app is restarted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub pid: String,
+    pub actor: String,
+    pub workspace: PathBuf,
+}
+
+/// List the sessions of the given context.
+pub fn list(context: &str) -> Vec<Session> {
+    match load() {
+        Ok(mut sessions) => sessions.remove(context).unwrap_or_default(),
+        Err(err) => {
+            warn!("Failed to load the live-sync sessions: {}", err);
+            vec![]
+        }
+    }
+}
+
+/// Record the session, replacing the previous one of the same actor.
+pub fn add(context: &str, session: Session) -> Result<()> {
+    update(context, |sessions| {
+        sessions.retain(|s| s.pid != session.pid || s.actor != session.actor);
+        sessions.push(session);
+    })
+}
+
+/// Forget the sessions of the given playbook.
+pub fn remove(context: &str, pid: &str) -> Result<()> {
+    update(context, |sessions| sessions.retain(|s| s.pid != pid))
+}
+
+/// Forget the session of the given actor.
+pub fn remove_actor(context: &str, pid: &str, actor: &str) -> Result<()> {
+    update(context, |sessions| {
+        sessions.retain(|s| s.pid != pid || s.actor != actor)
+    })
+}
+
+fn update(context: &str, f: impl FnOnce(&mut Vec<Session>)) -> Result<()> {
+    let mut all = load()?;
+    let sessions = all.entry(context.to_string()).or_default();
+    f(sessions);
+    if sessions.is_empty() {
+        all.remove(context);
+    }
+
+    storage::save(&location()?, &all)
+}
+
+fn load() -> Result<HashMap<String, Vec<Session>>> {
+    Ok(storage::load(&location()?)?.unwrap_or_default())
+}
+
+fn location() -> Result<PathBuf> {
+    Ok(storage::data_dir()?.join("sessions.json"))
+}
@@ -0,0 +1,120 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::errors::Result;
+use crate::utils::storage;
+
+/// The settings of the desktop app for a context.
+///
+/// They are kept apart from the amp configuration, which is shared with
+/// the CLI and knows nothing about them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub sync: SyncSettings,
+}
+
+impl Settings {
+    /// Load the settings of the given context, or the defaults if there are none.
+    pub fn load(context: &str) -> Self {
+        let settings: Result<Option<HashMap<String, Settings>>> = location().and_then(|path| storage::load(&path));
+        match settings {
+            Ok(settings) => settings.and_then(|mut s| s.remove(context)).unwrap_or_default(),
+            Err(err) => {
+                warn!("Failed to load the settings, using the defaults: {}", err);
+                Settings::default()
+            }
+        }
+    }
+}
+
+/// The settings of the file synchronization.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// The window in milliseconds in which file changes are batched.
+    pub debounce_ms: u64,
+    pub compression: Compression,
+    /// The maximum size in bytes of a workspace to upload, if limited.
+    pub max_workspace_size: Option<u64>,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 300,
+            compression: Compression::default(),
+            max_workspace_size: Some(1024 * 1024 * 1024),
+        }
+    }
+}
+
+impl SyncSettings {
+    pub fn debounce(&self) -> Duration {
+        Duration::from_millis(self.debounce_ms)
+    }
+}
+
+/// The compression of sync payloads.
+///
+/// The algorithm is named in the `encoding` attribute of every compressed
+/// sync, the server must support it, so payloads are plain by default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Compression {
+    pub algorithm: Algorithm,
+    /// Payloads smaller than this many bytes are sent as they are.
+    pub threshold: usize,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::default(),
+            threshold: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Algorithm::None => write!(f, "none"),
+            Algorithm::Gzip => write!(f, "gzip"),
+            Algorithm::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// The settings of every context, by name, see the README for the keys.
+fn location() -> Result<PathBuf> {
+    Ok(storage::data_dir()?.join("settings.json"))
+}
@@ -0,0 +1,50 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::{Errors, Result};
+
+/// The directory where the desktop app keeps its own state.
+pub fn data_dir() -> Result<PathBuf> {
+    dirs::data_local_dir()
+        .map(|dir| dir.join("amphitheatre-desktop"))
+        .ok_or(Errors::NotFoundDataDirectory)
+}
+
+/// Load a JSON document from the given path, `None` if it doesn't exist.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read(path).map_err(|e| Errors::FailedReadFile(e.to_string()))?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| Errors::SerdeJsonError(e.to_string()))
+}
+
+/// Save the value as a JSON document to the given path.
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Errors::FailedWriteFile(e.to_string()))?;
+    }
+
+    let content = serde_json::to_vec(value).map_err(|e| Errors::SerdeJsonError(e.to_string()))?;
+    fs::write(path, content).map_err(|e| Errors::FailedWriteFile(e.to_string()))
+}
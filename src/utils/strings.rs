@@ -47,3 +47,86 @@ pub fn generate_random_words_string(range: std::ops::Range<usize>) -> String {
 
     get_random_words(count).join(" ")
 }
+
+/// Format the size in bytes for humans, e.g. `1.5 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[0]),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+/// Match the query as a fuzzy subsequence of the text, ignoring case and
+/// the whitespace of the query. Returns the score, higher is better, and
+/// the char indices of the text matched.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let mut query = query.chars().filter(|c| !c.is_whitespace()).peekable();
+    query.peek()?;
+
+    let mut score = 0;
+    let mut indices = vec![];
+    let mut previous: Option<char> = None;
+    for (index, c) in text.chars().enumerate() {
+        let Some(&q) = query.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(q.to_lowercase()) {
+            score += 1;
+            // Favor runs of characters, and the starts of words.
+            if indices.last().is_some_and(|last| last + 1 == index) {
+                score += 5;
+            }
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            indices.push(index);
+            query.next();
+        }
+        previous = Some(c);
+    }
+
+    if query.peek().is_some() {
+        return None;
+    }
+    // Favor the matches starting early.
+    score -= indices[0].min(10) as i64;
+    Some((score, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match_subsequence() {
+        assert_eq!(
+            fuzzy_match("abc", "a-b-c").map(|(_, indices)| indices),
+            Some(vec![0, 2, 4])
+        );
+        assert_eq!(fuzzy_match("A B", "cab").map(|(_, indices)| indices), Some(vec![1, 2]));
+        assert!(fuzzy_match("abc", "acb").is_none());
+        assert!(fuzzy_match("", "anything").is_none());
+        assert!(fuzzy_match("  ", "anything").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_scoring() {
+        let score = |query, text| fuzzy_match(query, text).unwrap().0;
+
+        // A run of characters beats scattered ones.
+        assert!(score("api", "api-gateway") > score("api", "a-p-i"));
+        // The start of a word beats its middle.
+        assert!(score("gate", "api gate") > score("gate", "apigate"));
+        // An early match beats a late one.
+        assert!(score("web", "web-frontend") > score("web", "frontend-web"));
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use amp_client::client::Client;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::{JoinError, JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::utils::settings::SyncSettings;
+use crate::utils::watcher;
+
+/// The delay before the first restart of a dead watcher.
+const RESTART_DELAY_MIN: Duration = Duration::from_secs(1);
+/// The upper bound of the restart delay, a watcher that lived longer than
+/// this is considered healthy again.
+const RESTART_DELAY_MAX: Duration = Duration::from_secs(60);
+
+/// How many of the recently synced paths and errors are kept.
+const HISTORY_SIZE: usize = 50;
+
+/// The state of a live-sync watcher.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SyncState {
+    #[default]
+    Starting,
+    Watching,
+    /// The watcher died with the given reason and will be restarted.
+    Restarting(String),
+}
+
+impl Display for SyncState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncState::Starting => write!(f, "Starting..."),
+            SyncState::Watching => write!(f, "Watching"),
+            SyncState::Restarting(_) => write!(f, "Restarting..."),
+        }
+    }
+}
+
+/// The live-sync status of an actor, shared with its watcher.
+#[derive(Clone, Debug, Default)]
+pub struct SyncStatus {
+    pub state: SyncState,
+    /// The changes are queued but not sent while paused.
+    pub paused: bool,
+    pub last_synced: Option<Instant>,
+    /// The number of changes waiting to be sent.
+    pub pending: usize,
+    /// The recently synced paths, the latest first.
+    pub recent: VecDeque<String>,
+    /// The recent errors, the latest first.
+    pub errors: VecDeque<String>,
+}
+
+impl SyncStatus {
+    /// Record the paths that were synced just now.
+    pub fn synced(&mut self, paths: impl IntoIterator<Item = String>) {
+        self.last_synced = Some(Instant::now());
+        self.pending = 0;
+        for path in paths {
+            push_front(&mut self.recent, path);
+        }
+    }
+
+    pub fn failed(&mut self, err: impl ToString) {
+        push_front(&mut self.errors, err.to_string());
+    }
+}
+
+fn push_front(history: &mut VecDeque<String>, item: String) {
+    history.push_front(item);
+    history.truncate(HISTORY_SIZE);
+}
+
+/// The status of a watcher, shared between the watcher and the UI.
+pub type Status = Arc<RwLock<SyncStatus>>;
+
+/// The commands sent to a running watcher.
+#[derive(Clone, Copy, Debug)]
+pub enum Control {
+    /// Send the pending changes now, without waiting for the next retry.
+    Flush,
+    /// Drop the pending changes and upload the whole workspace.
+    Resync,
+}
+
+/// The key of a watcher, the playbook id and the actor name.
+type Key = (String, String);
+
+struct Worker {
+    status: Status,
+    control: UnboundedSender<Control>,
+    handle: JoinHandle<()>,
+}
+
+/// Supervisor owns the live-sync watchers of the app, one for each actor.
+///
+/// It restarts the watchers when they die, and stops them when the
+/// playbook they belong to goes away.
+#[derive(Clone, Default)]
+pub struct Supervisor(Arc<Mutex<HashMap<Key, Worker>>>);
+
+impl Supervisor {
+    /// Start watching the workspace for the given actor, replacing the
+    /// existing watcher if any. Must be called within a tokio runtime.
+    pub fn start(&self, client: Arc<Client>, settings: SyncSettings, pid: &str, actor: &str, workspace: PathBuf) {
+        let key = (pid.to_string(), actor.to_string());
+        let status = Status::default();
+        let (control, receiver) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(supervise(
+            client,
+            settings,
+            key.clone(),
+            workspace,
+            status.clone(),
+            receiver,
+        ));
+
+        let worker = Worker {
+            status,
+            control,
+            handle,
+        };
+        if let Some(worker) = self.0.lock().unwrap().insert(key, worker) {
+            worker.handle.abort();
+        }
+    }
+
+    /// Stop the watcher of the given actor.
+    pub fn stop(&self, pid: &str, actor: &str) {
+        let key = (pid.to_string(), actor.to_string());
+        if let Some(worker) = self.0.lock().unwrap().remove(&key) {
+            info!("Stopping the watcher of {}/{}", pid, actor);
+            worker.handle.abort();
+        }
+    }
+
+    /// Stop all watchers of the given playbook.
+    pub fn stop_playbook(&self, pid: &str) {
+        self.0.lock().unwrap().retain(|(id, actor), worker| {
+            if id != pid {
+                return true;
+            }
+            info!("Stopping the watcher of {}/{}", id, actor);
+            worker.handle.abort();
+            false
+        });
+    }
+
+    /// Stop all watchers.
+    pub fn stop_all(&self) {
+        for ((pid, actor), worker) in self.0.lock().unwrap().drain() {
+            info!("Stopping the watcher of {}/{}", pid, actor);
+            worker.handle.abort();
+        }
+    }
+
+    /// Get the status of the watcher for the given actor, if it's supervised.
+    pub fn status(&self, pid: &str, actor: &str) -> Option<SyncStatus> {
+        let key = (pid.to_string(), actor.to_string());
+        self.0
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|worker| worker.status.read().unwrap().clone())
+    }
+
+    /// Stop sending the changes of the given actor, they are queued until
+    /// it's resumed.
+    pub fn pause(&self, pid: &str, actor: &str) {
+        self.with(pid, actor, |worker| {
+            info!("Pausing the watcher of {}/{}", pid, actor);
+            worker.status.write().unwrap().paused = true;
+        });
+    }
+
+    /// Resume the paused watcher and send the queued changes.
+    pub fn resume(&self, pid: &str, actor: &str) {
+        self.with(pid, actor, |worker| {
+            info!("Resuming the watcher of {}/{}", pid, actor);
+            worker.status.write().unwrap().paused = false;
+            let _ = worker.control.send(Control::Flush);
+        });
+    }
+
+    /// Upload the whole workspace of the given actor again.
+    pub fn resync(&self, pid: &str, actor: &str) {
+        self.with(pid, actor, |worker| {
+            info!("Resyncing the workspace of {}/{}", pid, actor);
+            let _ = worker.control.send(Control::Resync);
+        });
+    }
+
+    fn with(&self, pid: &str, actor: &str, f: impl FnOnce(&Worker)) {
+        let key = (pid.to_string(), actor.to_string());
+        if let Some(worker) = self.0.lock().unwrap().get(&key) {
+            f(worker);
+        }
+    }
+}
+
+/// Run the watcher until it's aborted, restarting it with backoff.
+async fn supervise(
+    client: Arc<Client>,
+    settings: SyncSettings,
+    key: Key,
+    workspace: PathBuf,
+    status: Status,
+    control: mpsc::UnboundedReceiver<Control>,
+) {
+    let (pid, actor) = key;
+    let control = Arc::new(tokio::sync::Mutex::new(control));
+    let mut delay = RESTART_DELAY_MIN;
+
+    loop {
+        status.write().unwrap().state = SyncState::Watching;
+        let started = Instant::now();
+
+        // Each run has a task of its own, so that a panic is a death like
+        // any other rather than the end of the supervisor.
+        let run = {
+            let (client, settings, pid, actor) = (client.clone(), settings.clone(), pid.clone(), actor.clone());
+            let (workspace, status, control) = (workspace.clone(), status.clone(), control.clone());
+            Task(tokio::spawn(async move {
+                let mut control = control.lock().await;
+                watcher::watch(&workspace, &client, &settings, &pid, &actor, &status, &mut control).await
+            }))
+        };
+        let reason = match run.join().await {
+            Ok(Ok(_)) => String::from("The watcher exited unexpectedly"),
+            Ok(Err(err)) => err.to_string(),
+            Err(err) => {
+                // The status may have been held by the watcher when it panicked.
+                status.clear_poison();
+                format!("The watcher panicked: {}", err)
+            }
+        };
+        error!("The watcher of {}/{} is stopped: {}", pid, actor, reason);
+
+        if started.elapsed() > RESTART_DELAY_MAX {
+            delay = RESTART_DELAY_MIN;
+        }
+
+        warn!("Restarting the watcher of {}/{} in {:?}", pid, actor, delay);
+        {
+            let mut status = status.write().unwrap();
+            status.failed(&reason);
+            status.state = SyncState::Restarting(reason);
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(RESTART_DELAY_MAX);
+    }
+}
+
+/// A spawned task, aborted when dropped, so that the run of a watcher ends
+/// along with the supervisor.
+struct Task<T>(JoinHandle<T>);
+
+impl<T> Task<T> {
+    async fn join(mut self) -> Result<T, JoinError> {
+        (&mut self.0).await
+    }
+}
+
+impl<T> Drop for Task<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
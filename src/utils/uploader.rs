@@ -12,59 +12,520 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use flate2::write::GzEncoder;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use tar::{Builder, EntryType, Header, HeaderMode};
+use tracing::{debug, info, warn};
+
+use amp_client::client::Client;
+use amp_common::sync::{self, Attributes, EventKinds, Synchronization};
 use ignore::WalkBuilder;
-use std::path::{Path, PathBuf};
-use tar::Builder;
-use tracing::debug;
 
-use amp_common::sync::{EventKinds, Synchronization};
+use crate::errors::{Errors, Result};
+use crate::utils::manifest::Manifest;
+use crate::utils::matcher::{self, Matcher};
+use crate::utils::progress::{Progress, Reporter};
+use crate::utils::settings::{Algorithm, Compression, SyncSettings};
+
+/// The maximum number of file bytes packed into a single sync request.
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+/// The number of the largest files listed in a preview.
+const PREVIEW_LARGEST: usize = 10;
+
+/// The paths to upload, both the full and the relative ones.
+type Paths = Vec<(PathBuf, PathBuf)>;
+/// The size of each file to upload, by its relative path.
+type Sizes = HashMap<PathBuf, u64>;
+
+/// What an upload of a workspace would send, and what it would leave out.
+#[derive(Clone, Debug, Default)]
+pub struct Preview {
+    pub files: usize,
+    pub total: u64,
+    /// The configured maximum size of a workspace.
+    pub limit: Option<u64>,
+    /// The largest files with their sizes, the largest first.
+    pub largest: Vec<(PathBuf, u64)>,
+    /// The excluded paths with the rule excluding them and their sizes,
+    /// the largest first.
+    pub excluded: Vec<(PathBuf, String, u64)>,
+}
+
+impl Preview {
+    pub fn exceeds_limit(&self) -> bool {
+        self.limit.is_some_and(|limit| self.total > limit)
+    }
+}
 
-use crate::{
-    context::Context,
-    errors::{Errors, Result},
-};
+/// Preview the upload of the given workspace without sending anything.
+pub fn preview(workspace: &Path, settings: &SyncSettings) -> Result<Preview> {
+    let (paths, sizes) = collect(workspace, None)?;
+
+    let mut largest: Vec<_> = paths
+        .iter()
+        .filter(|(path, _)| path.is_file())
+        .map(|(_, name)| (name.clone(), sizes[name]))
+        .collect();
+    largest.sort_by_key(|(_, size)| Reverse(*size));
+    let files = largest.len();
+    largest.truncate(PREVIEW_LARGEST);
+
+    let mut excluded = vec![];
+    let mut matcher = Matcher::new(workspace);
+    exclusions(&mut matcher, workspace, workspace, &mut excluded)?;
+    excluded.sort_by_key(|(_, _, size)| Reverse(*size));
+
+    Ok(Preview {
+        files,
+        total: sizes.values().sum(),
+        limit: settings.max_workspace_size,
+        largest,
+        excluded,
+    })
+}
+
+/// Find the excluded paths below the given directory, an excluded
+/// directory is reported as a whole.
+fn exclusions(
+    matcher: &mut Matcher,
+    base: &Path,
+    dir: &Path,
+    excluded: &mut Vec<(PathBuf, String, u64)>,
+) -> Result<()> {
+    let entries = std::fs::read_dir(dir).map_err(|e| Errors::FailedReadFile(e.to_string()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if let Some(rule) = matcher.explain(&path) {
+            let (path, name) = strip(base, &path)?;
+            excluded.push((name, rule, disk_usage(&path)));
+            continue;
+        }
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            exclusions(matcher, base, &path, excluded)?;
+        }
+    }
+    Ok(())
+}
+
+/// The total size of the files at the given path, ignore rules aside.
+fn disk_usage(path: &Path) -> u64 {
+    WalkBuilder::new(path)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
 
 /// Upload the given directory to the server.
-pub async fn upload(ctx: &Context, pid: &str, actor: &str, workspace: &Path) -> Result<()> {
-    let mut paths: Vec<(PathBuf, PathBuf)> = vec![];
+///
+/// Only the files changed since the last upload of this actor are sent, or
+/// the entire directory when there is no manifest of a previous upload or
+/// when `force` is set, e.g. when the actor has drifted from it. The files
+/// are sent in chunks, with the progress published to the reporter.
+pub async fn upload(
+    client: &Client,
+    settings: &SyncSettings,
+    pid: &str,
+    actor: &str,
+    workspace: &Path,
+    force: bool,
+    reporter: Option<&Reporter>,
+) -> Result<()> {
+    let result = transfer(client, settings, pid, actor, workspace, force, reporter).await;
+    if let Some(reporter) = reporter {
+        reporter.send_replace(None);
+    }
+    result
+}
+
+async fn transfer(
+    client: &Client,
+    settings: &SyncSettings,
+    pid: &str,
+    actor: &str,
+    workspace: &Path,
+    force: bool,
+    reporter: Option<&Reporter>,
+) -> Result<()> {
+    // Walking and hashing the workspace may take a while.
+    let (paths, sizes, previous, manifest) = {
+        let workspace = workspace.to_path_buf();
+        let limit = settings.max_workspace_size;
+        let (pid, actor) = (pid.to_string(), actor.to_string());
+        blocking(move || {
+            let (paths, sizes) = collect(&workspace, limit)?;
+            let previous = Manifest::load(&pid, &actor).filter(|_| !force);
+            let manifest = Manifest::build(&paths, previous.as_ref())?;
+            Ok((paths, sizes, previous, manifest))
+        })
+        .await?
+    };
+
+    // Without a usable manifest the server state is unknown, so the first
+    // chunk overwrites the workspace and the rest are added on top of it.
+    let (kind, paths) = match &previous {
+        Some(previous) => {
+            let diff = previous.diff(&manifest);
+            if diff.is_empty() {
+                info!("The workspace is already up to date");
+                return Ok(());
+            }
+            info!(
+                "Syncing {} changed and {} removed files",
+                diff.changed.len(),
+                diff.removed.len()
+            );
+
+            let removed: Vec<_> = diff.removals().collect();
+            if !removed.is_empty() {
+                let req = Synchronization {
+                    kind: EventKinds::Remove,
+                    paths: removed
+                        .iter()
+                        .map(|name| format_path(name, previous.is_dir(name)))
+                        .collect(),
+                    attributes: None,
+                    payload: None,
+                };
+                send(client, pid, actor, req).await?;
+            }
+
+            let changed = paths
+                .into_iter()
+                .filter(|(_, name)| diff.changed.contains(name))
+                .collect();
+            (EventKinds::Modify, changed)
+        }
+        None => (EventKinds::Overwrite, paths),
+    };
+
+    let chunks = chunk(paths, &sizes);
+    let mut progress = Progress::new(
+        actor,
+        chunks.iter().map(|c| c.len()).sum(),
+        chunks.iter().flatten().map(|(_, name)| sizes[name]).sum(),
+    );
+
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let compression = settings.compression.clone();
+        let (chunk, payload) = blocking(move || {
+            let payload = archive(&chunk, &compression)?;
+            Ok((chunk, payload))
+        })
+        .await?;
+        // Only the first chunk may overwrite the workspace, the rest are
+        // added on top of it and name the paths they carry.
+        let kind = if index == 0 { kind.clone() } else { EventKinds::Modify };
+        let req = Synchronization {
+            paths: match kind {
+                EventKinds::Overwrite => vec![],
+                _ => chunk
+                    .iter()
+                    .map(|(path, name)| format_path(name, path.symlink_metadata().is_ok_and(|m| m.is_dir())))
+                    .collect(),
+            },
+            kind,
+            attributes: payload.attributes(),
+            payload: Some(payload.data),
+        };
+        send(client, pid, actor, req).await?;
+
+        progress.advance(chunk.len(), chunk.iter().map(|(_, name)| sizes[name]).sum());
+        debug!("Uploaded {}", progress);
+        if let Some(reporter) = reporter {
+            reporter.send_replace(Some(progress.clone()));
+        }
+    }
+
+    if let Err(err) = manifest.save(pid, actor) {
+        warn!("Failed to save the manifest of {}/{}: {}", pid, actor, err);
+    }
+
+    Ok(())
+}
+
+/// Check that the workspace is within the maximum size of the settings,
+/// before anything is created for it on the server.
+pub async fn check(workspace: &Path, settings: &SyncSettings) -> Result<()> {
+    let workspace = workspace.to_path_buf();
+    let limit = settings.max_workspace_size;
+    blocking(move || collect(&workspace, limit).map(|_| ())).await
+}
+
+/// Collect the paths to upload from the workspace, with the size of each
+/// file, failing once the total exceeds the limit.
+fn collect(workspace: &Path, limit: Option<u64>) -> Result<(Paths, Sizes)> {
+    let mut paths: Paths = vec![];
+    let mut sizes: Sizes = HashMap::new();
+    let mut total: u64 = 0;
 
-    let base = workspace;
-    for entry in WalkBuilder::new(workspace).build() {
+    for entry in matcher::walker(workspace).build() {
         let entry = entry.map_err(Errors::WalkError)?;
         let path = entry.path();
 
-        if path.is_dir() {
+        // Directories are kept as well, so that empty ones are not lost.
+        if entry.depth() == 0 {
             continue;
         }
 
-        paths.push(strip(base, path)?);
+        let size = match entry.file_type() {
+            Some(file_type) if file_type.is_file() => entry.metadata().map(|m| m.len()).unwrap_or_default(),
+            _ => 0,
+        };
+        total += size;
+        if let Some(limit) = limit {
+            if total > limit {
+                return Err(Errors::WorkspaceTooLarge(limit));
+            }
+        }
+
+        let (path, name) = strip(workspace, path)?;
+        sizes.insert(name.clone(), size);
+        paths.push((path, name));
     }
 
-    let payload = archive(&paths)?;
-    let req = Synchronization {
-        kind: EventKinds::Overwrite,
-        paths: vec![],
-        attributes: None,
-        payload: Some(payload),
-    };
-    ctx.client()
+    Ok((paths, sizes))
+}
+
+/// Split the files into chunks of about `CHUNK_SIZE` bytes, a file larger
+/// than that makes up a chunk on its own.
+///
+/// The client takes a payload as a whole, and a sync request can't append
+/// to a file, so such a chunk is still held in memory entirely.
+fn chunk(paths: Vec<(PathBuf, PathBuf)>, sizes: &HashMap<PathBuf, u64>) -> Vec<Vec<(PathBuf, PathBuf)>> {
+    let mut chunks = vec![];
+    let mut current = vec![];
+    let mut size = 0;
+
+    for (path, name) in paths {
+        let len = sizes[&name];
+        if !current.is_empty() && size + len > CHUNK_SIZE {
+            chunks.push(std::mem::take(&mut current));
+            size = 0;
+        }
+        size += len;
+        current.push((path, name));
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+async fn send(client: &Client, pid: &str, actor: &str, req: Synchronization) -> Result<()> {
+    client
         .actors()
         .sync(pid, actor, req)
         .await
         .map_err(|e| Errors::ClientError(e.to_string()))?;
-
     Ok(())
 }
 
-/// Archive the given directory into a tarball and return the bytes.
-pub fn archive(paths: &Vec<(PathBuf, PathBuf)>) -> Result<Vec<u8>> {
+#[inline]
+fn format_path(name: &Path, is_dir: bool) -> sync::Path {
+    let name = name.to_string_lossy().to_string();
+    match is_dir {
+        true => sync::Path::Directory(name),
+        false => sync::Path::File(name),
+    }
+}
+
+/// A sync payload and the algorithm it's compressed with.
+pub struct Payload {
+    pub data: Vec<u8>,
+    pub algorithm: Algorithm,
+}
+
+impl Payload {
+    /// The attributes telling the server how the payload is encoded, there
+    /// are none for a plain tarball.
+    pub fn attributes(&self) -> Option<Attributes> {
+        match self.algorithm {
+            Algorithm::None => None,
+            algorithm => Some(Attributes {
+                encoding: Some(algorithm.to_string()),
+            }),
+        }
+    }
+}
+
+/// Archive the given directory into a tarball and return the payload,
+/// compressed as configured if it's large enough to be worth it.
+///
+/// The files are read from disk straight into the encoder, so only the
+/// encoded payload is held in memory.
+///
+/// The file modes are kept, directories are added as they are so that
+/// empty ones survive, and symlinks are added as links rather than the
+/// files they point to.
+pub fn archive(paths: &[(PathBuf, PathBuf)], compression: &Compression) -> Result<Payload> {
     debug!("The given path for archive is {:?}", paths);
-    let mut tar = Builder::new(Vec::new());
+    let size: u64 = paths
+        .iter()
+        .filter_map(|(path, _)| path.symlink_metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum();
+    let algorithm = match size < compression.threshold as u64 {
+        true => Algorithm::None,
+        false => compression.algorithm,
+    };
+
+    let encoder = Encoder::new(algorithm).map_err(|e| Errors::FailedCompress(e.to_string()))?;
+    let mut tar = Builder::new(encoder);
+    tar.follow_symlinks(false);
     for (path, name) in paths {
-        tar.append_path_with_name(path, name)
-            .map_err(|e| Errors::FailedAppendPath(e.to_string()))?;
+        append(&mut tar, path, name).map_err(|e| Errors::FailedAppendPath(e.to_string()))?;
+    }
+    let encoder = tar.into_inner().map_err(|e| Errors::FailedFinishTar(e.to_string()))?;
+    let data = encoder.finish().map_err(|e| Errors::FailedCompress(e.to_string()))?;
+    if algorithm != Algorithm::None {
+        debug!(
+            "Compressed the payload with {:?} from {} to {} bytes",
+            algorithm,
+            size,
+            data.len()
+        );
+    }
+
+    Ok(Payload { data, algorithm })
+}
+
+fn append(tar: &mut Builder<Encoder>, path: &Path, name: &Path) -> std::io::Result<()> {
+    let metadata = path.symlink_metadata()?;
+    let mut header = Header::new_gnu();
+    header.set_metadata_in_mode(&metadata, HeaderMode::Complete);
+    header.set_mode(mode(path, &metadata));
+
+    if metadata.is_symlink() {
+        let target = std::fs::read_link(path)?;
+        if !is_contained(name, &target) {
+            warn!(
+                "Skipped the symlink {:?}, its target {:?} is outside of the workspace",
+                name, target
+            );
+            return Ok(());
+        }
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        return tar.append_link(&mut header, name, target);
+    }
+
+    if metadata.is_dir() {
+        header.set_entry_type(EntryType::Directory);
+        header.set_size(0);
+        return tar.append_data(&mut header, name, std::io::empty());
+    }
+
+    tar.append_data(&mut header, name, File::open(path)?)
+}
+
+/// The permission bits of the given file.
+#[cfg(unix)]
+pub fn mode(_path: &Path, metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+/// The permission bits of the given file.
+///
+/// There is no executable bit on this platform, so scripts are told apart
+/// by their shebang, otherwise they can't be run on the actor.
+#[cfg(not(unix))]
+pub fn mode(path: &Path, metadata: &Metadata) -> u32 {
+    use std::io::Read;
+
+    if metadata.is_dir() {
+        return 0o755;
+    }
+
+    let mut shebang = [0u8; 2];
+    let executable = File::open(path)
+        .and_then(|mut file| file.read_exact(&mut shebang))
+        .is_ok_and(|_| &shebang == b"#!");
+    match executable {
+        true => 0o755,
+        false => 0o644,
+    }
+}
+
+/// Check whether the target of the symlink at `name` stays within the
+/// workspace, an absolute target never does.
+fn is_contained(name: &Path, target: &Path) -> bool {
+    let mut depth = name.components().count().saturating_sub(1);
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
     }
-    tar.into_inner().map_err(|e| Errors::FailedFinishTar(e.to_string()))
+    true
+}
+
+/// Encoder writes a payload, compressing it on the fly.
+enum Encoder {
+    Plain(Vec<u8>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Zstd(zstd::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(algorithm: Algorithm) -> std::io::Result<Self> {
+        Ok(match algorithm {
+            Algorithm::None => Encoder::Plain(Vec::new()),
+            Algorithm::Gzip => Encoder::Gzip(GzEncoder::new(Vec::new(), flate2::Compression::default())),
+            Algorithm::Zstd => Encoder::Zstd(zstd::Encoder::new(Vec::new(), 0)?),
+        })
+    }
+
+    /// Finish the payload and return its bytes.
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoder::Plain(data) => Ok(data),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(data) => data.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(data) => data.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Run the given work, e.g. walking or archiving the workspace, on a thread
+/// where blocking doesn't hold up the async runtime.
+pub async fn blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Errors::BlockingTaskError(e.to_string()))?
 }
 
 /// Strip the given base path from the given path.
@@ -76,3 +537,23 @@ pub fn strip(base: &Path, path: &Path) -> Result<(PathBuf, PathBuf)> {
     debug!("the full path and striped path is: {:?}, {:?}", path, striped_path);
     Ok((path.to_path_buf(), striped_path.to_path_buf()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symlink_contained() {
+        assert!(is_contained(Path::new("link"), Path::new("src/main.rs")));
+        assert!(is_contained(Path::new("a/link"), Path::new("../b/file")));
+        assert!(is_contained(Path::new("a/b/link"), Path::new("./c/../../d")));
+    }
+
+    #[test]
+    fn symlink_escaping() {
+        assert!(!is_contained(Path::new("link"), Path::new("../outside")));
+        assert!(!is_contained(Path::new("a/link"), Path::new("../../outside")));
+        assert!(!is_contained(Path::new("a/link"), Path::new("b/../../../outside")));
+        assert!(!is_contained(Path::new("link"), Path::new("/etc/passwd")));
+    }
+}
@@ -12,114 +12,688 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use amp_client::client::Client;
 use amp_common::sync::{self, EventKinds, Synchronization};
-use ignore::gitignore::{Gitignore, GitignoreBuilder};
-use notify::event::RemoveKind;
-use notify::EventKind::Remove;
+use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+use notify::EventKind::{Create, Modify, Remove};
 use notify::RecursiveMode::Recursive;
 use notify::{Event, RecommendedWatcher, Watcher};
-use tracing::{debug, error, trace, warn};
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, timeout_at, Instant};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::errors::{Errors, Result};
+use crate::utils::manifest::Manifest;
+use crate::utils::matcher::{self, Matcher};
+use crate::utils::settings::SyncSettings;
+use crate::utils::supervisor::{Control, Status};
 use crate::utils::uploader;
 
+/// The maximum number of paths queued while the server is unreachable,
+/// beyond which the queue is dropped in favor of a full upload.
+const MAX_PENDING: usize = 10_000;
+
+/// The delay before the first retry of a failed sync.
+const RETRY_DELAY_MIN: Duration = Duration::from_secs(1);
+/// The upper bound of the retry delay.
+const RETRY_DELAY_MAX: Duration = Duration::from_secs(60);
+
 ///  Watch file changes and sync the changed files.
-pub async fn watch(workspace: &Path, client: &Client, pid: &str, name: &str) -> Result<()> {
-    let (tx, rx) = std::sync::mpsc::channel();
+///
+/// Events are collected for a short window after the first one arrives, merged
+/// per path, and then sent to the server as a single batch. Failed batches
+/// stay queued and are retried with exponential backoff, or until the
+/// watcher is resumed when it's paused.
+pub async fn watch(
+    workspace: &Path,
+    client: &Client,
+    settings: &SyncSettings,
+    pid: &str,
+    name: &str,
+    status: &Status,
+    control: &mut mpsc::UnboundedReceiver<Control>,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
 
     // We listen to the file changes giving Notify
-    // a function that will get called when events happen.
+    // a function that will get called when events happen,
+    // it runs on the watcher's own thread and never blocks.
+    let handler = move |event| {
+        let _ = tx.send(event);
+    };
     let config = notify::Config::default();
-    let mut watcher = RecommendedWatcher::new(tx, config).map_err(|e| Errors::FailedCreateWatcher(e.to_string()))?;
+    let mut watcher =
+        RecommendedWatcher::new(handler, config).map_err(|e| Errors::FailedCreateWatcher(e.to_string()))?;
     watcher
         .watch(workspace, Recursive)
         .map_err(|e| Errors::FailedWatchDirectory(e.to_string()))?;
 
-    let mut builder = GitignoreBuilder::new(workspace);
-    builder.add(".gitignore");
-    let matcher = builder.build().unwrap();
+    let dirs = {
+        let workspace = workspace.to_path_buf();
+        uploader::blocking(move || Ok(Directories::scan(&workspace))).await?
+    };
+    let mut syncer = Syncer::new(client, settings, pid, name, workspace, dirs, status);
 
-    for event in rx {
-        if let Err(err) = event {
-            error!("Got a notify error: {err:?}");
-            continue;
-        }
-        let event = event.unwrap();
-        if is_ignored(&matcher, workspace, &event.paths)? {
-            continue;
+    loop {
+        // Wait for the first event of a batch, a command, or until the
+        // next retry of the pending changes is due.
+        let deadline = syncer.deadline();
+        let event = tokio::select! {
+            event = rx.recv() => event,
+            Some(command) = control.recv() => {
+                syncer.control(command);
+                syncer.flush().await;
+                continue;
+            }
+            _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                syncer.flush().await;
+                continue;
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+
+        let mut changes = Changes::default();
+        changes.push(&mut syncer.tree.lock().unwrap().matcher, event);
+
+        // Then keep collecting until the debounce window is closed.
+        let deadline = Instant::now() + settings.debounce();
+        let mut disconnected = false;
+        loop {
+            match timeout_at(deadline, rx.recv()).await {
+                Ok(Some(event)) => changes.push(&mut syncer.tree.lock().unwrap().matcher, event),
+                Ok(None) => {
+                    disconnected = true;
+                    break;
+                }
+                Err(_) => break,
+            }
         }
 
-        handle(client, pid, name, workspace, event).await?;
+        syncer.enqueue(changes);
+        syncer.flush().await;
+
+        if disconnected {
+            break;
+        }
     }
 
     Ok(())
 }
 
-async fn handle(client: &Client, pid: &str, name: &str, base: &Path, event: Event) -> Result<()> {
-    trace!("Changed: {:?}", event);
+/// The merged state of a single path within a batch.
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    /// The path did not exist before this batch, so it's unknown to the server.
+    created: bool,
+    /// The path is known to be a directory, used when it was removed.
+    is_dir: bool,
+    /// The path was a rename source or target, so a directory found here
+    /// must be uploaded with all of its contents.
+    renamed: bool,
+}
+
+/// The pending changes of a batch, keyed and ordered by path.
+#[derive(Clone, Debug, Default)]
+struct Changes(BTreeMap<PathBuf, Change>);
+
+impl Changes {
+    /// Merge the given notify event into the pending changes.
+    fn push(&mut self, matcher: &mut Matcher, event: notify::Result<Event>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                error!("Got a notify error: {err:?}");
+                return;
+            }
+        };
+        trace!("Changed: {:?}", event);
+
+        let kind = EventKinds::from(event.kind);
+        if kind == EventKinds::Other {
+            warn!("Not supported event: {:?}", event);
+            return;
+        }
 
-    let kind = EventKinds::from(event.kind);
-    if kind == EventKinds::Rename || kind == EventKinds::Other {
-        warn!("Not supported event: {:?}", event);
-        return Ok(());
+        let is_dir = matches!(event.kind, Create(CreateKind::Folder) | Remove(RemoveKind::Folder));
+        let renamed = kind == EventKinds::Rename;
+
+        for (index, path) in event.paths.into_iter().enumerate() {
+            // Either side of a rename may live outside of the workspace,
+            // only the side we are watching is of interest.
+            matcher.reload(&path);
+            if matcher.is_ignored(&path) {
+                continue;
+            }
+
+            // A rename source was known to the server, while the target is
+            // new to it. When the direction is unknown, the file system
+            // decides what to do later on.
+            let created = match event.kind {
+                Modify(ModifyKind::Name(RenameMode::To)) => true,
+                Modify(ModifyKind::Name(RenameMode::Both)) => index > 0,
+                _ => kind == EventKinds::Create,
+            };
+
+            self.insert(
+                path,
+                Change {
+                    created,
+                    is_dir,
+                    renamed,
+                },
+            );
+        }
     }
 
-    let mut paths: Vec<(PathBuf, PathBuf)> = vec![];
-    for path in event.paths {
-        paths.push(uploader::strip(base, &path)?);
+    /// Merge a later batch into this one.
+    fn merge(&mut self, other: Changes) {
+        for (path, change) in other.0 {
+            self.insert(path, change);
+        }
     }
 
-    let mut req = Synchronization {
-        kind: kind.clone(),
-        paths: vec![],
-        attributes: None,
-        payload: None,
-    };
+    fn insert(&mut self, path: PathBuf, change: Change) {
+        // The first event decides whether the path is new, the later ones
+        // can only tell us more about it.
+        let current = self.0.entry(path).or_insert(change);
+        current.is_dir |= change.is_dir;
+        current.renamed |= change.renamed;
+    }
 
-    // Because the file or directory was removed yet, we can't get the file type.
-    // so we determine the file type by original event kind.
-    if kind == EventKinds::Remove {
-        let is_dir = event.kind == Remove(RemoveKind::Folder);
-        req.paths = paths.iter().map(|(_, b)| format_path(b, is_dir)).collect();
-    } else {
-        req.paths = paths.iter().map(|(a, b)| format_path(b, a.is_dir())).collect();
+    fn len(&self) -> usize {
+        self.0.len()
     }
 
-    if kind == EventKinds::Modify {
-        req.payload = Some(uploader::archive(&paths)?);
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
-    debug!("The sync request is: {:?}", req);
-    client
-        .actors()
-        .sync(pid, name, req)
-        .await
-        .map_err(|e| Errors::ClientError(e.to_string()))?;
+    /// Build the sync requests of this batch, at most one for each kind.
+    ///
+    /// The final state is taken from the file system, so a path that was
+    /// created and removed again within the batch produces nothing at all.
+    fn requests(
+        &mut self,
+        base: &Path,
+        dirs: &mut Directories,
+        matcher: &mut Matcher,
+        settings: &SyncSettings,
+    ) -> Result<Vec<Synchronization>> {
+        let mut removed: Vec<(PathBuf, bool)> = vec![];
+        let mut created: Vec<PathBuf> = vec![];
+        let mut modified: Vec<(PathBuf, PathBuf)> = vec![];
 
-    Ok(())
+        for (path, change) in self.0.iter_mut() {
+            let (path, name) = uploader::strip(base, path)?;
+            match path.symlink_metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    dirs.insert(&path);
+                    if change.created || change.renamed {
+                        created.push(name);
+                    }
+                    // Nothing is reported for the contents of a directory
+                    // moved into the workspace, so walk it ourselves.
+                    if change.renamed {
+                        for entry in matcher::walker(&path).build().skip(1) {
+                            let entry = entry.map_err(Errors::WalkError)?;
+                            // The matcher has the final say, as the other
+                            // events are checked against it.
+                            if matcher.is_ignored(entry.path()) {
+                                continue;
+                            }
+                            let (path, name) = uploader::strip(base, entry.path())?;
+                            if entry.file_type().is_some_and(|t| t.is_dir()) {
+                                dirs.insert(&path);
+                                created.push(name);
+                            } else {
+                                modified.push((path, name));
+                            }
+                        }
+                    }
+                }
+                Ok(_) => modified.push((path, name)),
+                Err(_) if change.created => {
+                    dirs.remove(&path);
+                }
+                Err(_) => {
+                    // The type of a vanished path can't be read anymore, so
+                    // fall back to what we have seen of it before. Remember
+                    // it, as these requests may have to be built again.
+                    change.is_dir |= dirs.remove(&path);
+                    let is_dir = change.is_dir;
+
+                    // Everything below a removed directory is gone with it.
+                    if !removed.iter().any(|(p, is_dir)| *is_dir && name.starts_with(p)) {
+                        removed.push((name, is_dir));
+                    }
+                }
+            }
+        }
+
+        // The same path may have been reached by a walk and an event.
+        created.sort();
+        created.dedup();
+        modified.sort();
+        modified.dedup();
+
+        let mut requests = vec![];
+        if !removed.is_empty() {
+            requests.push(Synchronization {
+                kind: EventKinds::Remove,
+                paths: removed.iter().map(|(p, is_dir)| format_path(p, *is_dir)).collect(),
+                attributes: None,
+                payload: None,
+            });
+        }
+        if !created.is_empty() {
+            requests.push(Synchronization {
+                kind: EventKinds::Create,
+                paths: created.iter().map(|p| format_path(p, true)).collect(),
+                attributes: None,
+                payload: None,
+            });
+        }
+        if !modified.is_empty() {
+            let payload = uploader::archive(&modified, &settings.compression)?;
+            requests.push(Synchronization {
+                kind: EventKinds::Modify,
+                paths: modified.iter().map(|(_, p)| format_path(p, false)).collect(),
+                attributes: payload.attributes(),
+                payload: Some(payload.data),
+            });
+        }
+
+        Ok(requests)
+    }
+}
+
+/// The directories known to exist in the workspace.
+///
+/// Once a path is gone there is no way to tell whether it was a file or a
+/// directory, and rename events don't carry that information either.
+#[derive(Debug, Default)]
+struct Directories(HashSet<PathBuf>);
+
+impl Directories {
+    /// Collect the directories of the given workspace.
+    fn scan(workspace: &Path) -> Self {
+        let dirs = matcher::walker(workspace)
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+            .map(|entry| entry.into_path())
+            .collect();
+
+        Directories(dirs)
+    }
+
+    fn insert(&mut self, path: &Path) {
+        self.0.insert(path.to_path_buf());
+    }
+
+    /// Forget the given path and everything below it, returns whether the
+    /// path was a known directory.
+    fn remove(&mut self, path: &Path) -> bool {
+        let known = self.0.remove(path);
+        if known {
+            self.0.retain(|p| !p.starts_with(path));
+        }
+        known
+    }
+}
+
+/// What the syncer knows of the workspace, shared with the blocking tasks
+/// that build the requests.
+struct Tree {
+    dirs: Directories,
+    matcher: Matcher,
+}
+
+/// Syncer sends the queued changes to the server, and keeps them while the
+/// server is unreachable.
+struct Syncer<'a> {
+    client: &'a Client,
+    settings: &'a SyncSettings,
+    pid: &'a str,
+    name: &'a str,
+    base: &'a Path,
+    tree: Arc<Mutex<Tree>>,
+    status: &'a Status,
+    /// The manifest of the last upload, kept up to date with what is
+    /// synced since, so that the next upload doesn't skip those paths.
+    manifest: Option<Manifest>,
+
+    pending: Changes,
+    /// Too many changes were queued, so a full upload is required.
+    overflowed: bool,
+    /// A resync was requested, so the full upload must overwrite the
+    /// workspace of the actor rather than trust the manifest.
+    forced: bool,
+    /// The time of the next attempt and the current delay, while failing.
+    retry: Option<(Instant, Duration)>,
+}
+
+impl<'a> Syncer<'a> {
+    fn new(
+        client: &'a Client,
+        settings: &'a SyncSettings,
+        pid: &'a str,
+        name: &'a str,
+        base: &'a Path,
+        dirs: Directories,
+        status: &'a Status,
+    ) -> Self {
+        Self {
+            client,
+            settings,
+            pid,
+            name,
+            base,
+            tree: Arc::new(Mutex::new(Tree {
+                dirs,
+                matcher: Matcher::new(base),
+            })),
+            status,
+            manifest: Manifest::load(pid, name),
+            pending: Changes::default(),
+            overflowed: false,
+            forced: false,
+            retry: None,
+        }
+    }
+
+    /// Queue the changes, compacting them with the pending ones.
+    fn enqueue(&mut self, changes: Changes) {
+        // The full upload will pick up these changes anyway.
+        if self.overflowed {
+            return;
+        }
+
+        self.pending.merge(changes);
+        if self.pending.len() > MAX_PENDING {
+            warn!("Too many pending changes, a full upload will be done instead");
+            self.pending = Changes::default();
+            self.overflowed = true;
+        }
+        self.status.write().unwrap().pending = self.pending.len();
+    }
+
+    fn control(&mut self, command: Control) {
+        match command {
+            Control::Flush => {}
+            Control::Resync => {
+                // The full upload covers the pending changes as well.
+                self.pending = Changes::default();
+                self.overflowed = true;
+                self.forced = true;
+            }
+        }
+        self.retry = None;
+    }
+
+    /// The time of the next attempt, if the pending changes are waiting
+    /// for a retry.
+    fn deadline(&self) -> Option<Instant> {
+        self.retry.map(|(at, _)| at)
+    }
+
+    /// Send the pending changes, unless we are waiting for the next retry.
+    async fn flush(&mut self) {
+        if self.pending.is_empty() && !self.overflowed {
+            return;
+        }
+        if self.status.read().unwrap().paused {
+            return;
+        }
+        if self.retry.is_some_and(|(at, _)| Instant::now() < at) {
+            return;
+        }
+
+        match self.send().await {
+            Ok(_) => {
+                if self.retry.take().is_some() {
+                    info!("The server is reachable again, all pending changes are synced");
+                }
+            }
+            Err(err) => {
+                let delay = match self.retry {
+                    Some((_, delay)) => (delay * 2).min(RETRY_DELAY_MAX),
+                    None => RETRY_DELAY_MIN,
+                };
+                warn!(
+                    "Failed to sync {} pending changes, retrying in {:?}: {}",
+                    self.pending.len(),
+                    delay,
+                    err
+                );
+                self.status.write().unwrap().failed(&err);
+                self.retry = Some((Instant::now() + delay, delay));
+            }
+        }
+    }
+
+    async fn send(&mut self) -> Result<()> {
+        if self.overflowed {
+            uploader::upload(
+                self.client,
+                self.settings,
+                self.pid,
+                self.name,
+                self.base,
+                self.forced,
+                None,
+            )
+            .await?;
+            self.manifest = Manifest::load(self.pid, self.name);
+            self.overflowed = false;
+            self.forced = false;
+            self.pending = Changes::default();
+            self.status
+                .write()
+                .unwrap()
+                .synced([String::from("The whole workspace")]);
+            return Ok(());
+        }
+
+        let mut synced = vec![];
+        for req in self.requests().await? {
+            debug!("The sync request is: {:?}", req);
+            let names: Vec<&String> = req
+                .paths
+                .iter()
+                .map(|path| match path {
+                    sync::Path::File(path) | sync::Path::Directory(path) => path,
+                })
+                .collect();
+            let paths: Vec<String> = names.iter().map(|path| format!("{:?} {}", req.kind, path)).collect();
+            let names: Vec<PathBuf> = names.into_iter().map(PathBuf::from).collect();
+            self.client
+                .actors()
+                .sync(self.pid, self.name, req)
+                .await
+                .map_err(|e| Errors::ClientError(e.to_string()))?;
+            synced.extend(paths);
+            self.record(names).await;
+        }
+        self.pending = Changes::default();
+        self.status.write().unwrap().synced(synced);
+
+        Ok(())
+    }
+
+    /// Build the requests of the pending changes on a blocking thread, as
+    /// it may walk directories and archive files.
+    async fn requests(&mut self) -> Result<Vec<Synchronization>> {
+        let mut pending = self.pending.clone();
+        let tree = self.tree.clone();
+        let base = self.base.to_path_buf();
+        let settings = self.settings.clone();
+        let (pending, requests) = uploader::blocking(move || {
+            let mut tree = tree.lock().unwrap();
+            let Tree { dirs, matcher } = &mut *tree;
+            let requests = pending.requests(&base, dirs, matcher, &settings);
+            Ok((pending, requests))
+        })
+        .await?;
+
+        // Keep what was learned about the changes, they may be sent again.
+        self.pending = pending;
+        requests
+    }
+
+    /// Record the synced paths in the manifest as they are now.
+    async fn record(&mut self, names: Vec<PathBuf>) {
+        let Some(manifest) = self.manifest.take() else {
+            return;
+        };
+
+        let base = self.base.to_path_buf();
+        let (pid, actor) = (self.pid.to_string(), self.name.to_string());
+        let result = uploader::blocking(move || Ok(record(manifest, &base, &names, &pid, &actor))).await;
+        self.manifest = result.unwrap_or_else(|err| {
+            warn!("Failed to record the synced paths in the manifest: {}", err);
+            forget(self.pid, self.name);
+            None
+        });
+    }
+}
+
+/// Record the given paths in the manifest and save it, it's deleted when
+/// a path can't be recorded.
+fn record(mut manifest: Manifest, base: &Path, names: &[PathBuf], pid: &str, actor: &str) -> Option<Manifest> {
+    for name in names {
+        if let Err(err) = manifest.record(name, &base.join(name)) {
+            // The manifest can't tell what the actor has anymore, so
+            // forget it and let the next upload send everything.
+            warn!("Failed to record {:?} in the manifest: {}", name, err);
+            forget(pid, actor);
+            return None;
+        }
+    }
+
+    if let Err(err) = manifest.save(pid, actor) {
+        warn!("Failed to save the manifest of {}/{}: {}", pid, actor, err);
+    }
+    Some(manifest)
+}
+
+/// Delete the manifest of the given actor.
+fn forget(pid: &str, actor: &str) {
+    if let Err(err) = Manifest::delete(pid, actor) {
+        warn!("Failed to delete the manifest of {}/{}: {}", pid, actor, err);
+    }
 }
 
 fn format_path(path: &Path, is_dir: bool) -> sync::Path {
-    let path_string = path.to_str().unwrap().to_string();
+    let path_string = path.to_string_lossy().to_string();
     match is_dir {
         true => sync::Path::Directory(path_string),
         false => sync::Path::File(path_string),
     }
 }
 
-fn is_ignored(matcher: &Gitignore, root: &Path, paths: &Vec<PathBuf>) -> Result<bool> {
-    for path in paths {
-        let name = path
-            .strip_prefix(root)
-            .map_err(|e| Errors::FailedStripPrefix(e.to_string()))?;
-        if matcher.matched(name, false).is_ignore() {
-            debug!("The file is ignored: {:?}", name);
-            return Ok(true);
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn change(created: bool, renamed: bool) -> Change {
+        Change {
+            created,
+            is_dir: false,
+            renamed,
+        }
+    }
+
+    /// Build the requests of the changes, as their kinds and path names.
+    fn requests(base: &Path, dirs: &mut Directories, changes: &[(&str, Change)]) -> Vec<(EventKinds, Vec<String>)> {
+        let mut pending = Changes::default();
+        for (name, change) in changes {
+            pending.insert(base.join(name), *change);
         }
+
+        let mut matcher = Matcher::new(base);
+        let settings = SyncSettings::default();
+        pending
+            .requests(base, dirs, &mut matcher, &settings)
+            .unwrap()
+            .into_iter()
+            .map(|req| {
+                let paths = req
+                    .paths
+                    .into_iter()
+                    .map(|path| match path {
+                        sync::Path::File(name) | sync::Path::Directory(name) => name,
+                    })
+                    .collect();
+                (req.kind, paths)
+            })
+            .collect()
     }
 
-    Ok(false)
+    #[test]
+    fn rename_pair() {
+        let workspace = tempfile::tempdir().unwrap();
+        let base = workspace.path();
+        fs::write(base.join("new.rs"), "fn main() {}").unwrap();
+
+        let mut dirs = Directories::default();
+        let requests = requests(
+            base,
+            &mut dirs,
+            &[("old.rs", change(false, true)), ("new.rs", change(true, true))],
+        );
+        assert_eq!(
+            requests,
+            vec![
+                (EventKinds::Remove, vec![String::from("old.rs")]),
+                (EventKinds::Modify, vec![String::from("new.rs")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn removal_inside_surviving_directory() {
+        let workspace = tempfile::tempdir().unwrap();
+        let base = workspace.path();
+        fs::create_dir(base.join("src")).unwrap();
+
+        let mut dirs = Directories::scan(base);
+        let requests = requests(base, &mut dirs, &[("src/old.rs", change(false, false))]);
+        assert_eq!(requests, vec![(EventKinds::Remove, vec![String::from("src/old.rs")])]);
+        assert!(dirs.0.contains(&base.join("src")));
+    }
+
+    #[test]
+    fn removed_directory_covers_its_contents() {
+        let workspace = tempfile::tempdir().unwrap();
+        let base = workspace.path();
+
+        let mut dirs = Directories::default();
+        dirs.insert(&base.join("old"));
+        let requests = requests(
+            base,
+            &mut dirs,
+            &[("old", change(false, false)), ("old/a.rs", change(false, false))],
+        );
+        assert_eq!(requests, vec![(EventKinds::Remove, vec![String::from("old")])]);
+    }
+
+    #[test]
+    fn created_and_removed_within_batch() {
+        let workspace = tempfile::tempdir().unwrap();
+        let base = workspace.path();
+
+        let mut dirs = Directories::default();
+        let requests = requests(base, &mut dirs, &[("tmp.rs", change(true, false))]);
+        assert!(requests.is_empty());
+    }
 }
@@ -15,22 +15,34 @@
 use std::sync::Arc;
 
 use iced::widget::space;
+use iced::widget::tooltip;
+use iced::widget::tooltip::Position;
 use iced::{Alignment, Length, Subscription, Task};
 use iced_fonts::bootstrap;
+use native_dialog::DialogBuilder;
+use tracing::error;
 
 use amp_common::resource::{CharacterSpec, PlaybookSpec};
 
+use crate::cmd::playbook::{attach, detach};
 use crate::context::Context;
+use crate::errors::Result;
 use crate::styles::{self, constants::*};
+use crate::utils::progress::Reporter;
+use crate::utils::supervisor::{Supervisor, SyncState};
 use crate::views::detail::inspect::{self, Information};
 use crate::views::detail::logs::{self, Logs};
 use crate::views::detail::stats::{self, Stats};
+use crate::views::detail::sync::{self, LiveSync};
 use crate::widgets::character_switcher::{self, *};
 use crate::widgets::tabs::Tab;
 use crate::widgets::{rule, Button, Column, Container, Element, Row, Tabs, Text};
 
 // #[derive(Default)]
 pub struct Body {
+    ctx: Context,
+    supervisor: Supervisor,
+    reporter: Reporter,
     playbook: Arc<PlaybookSpec>,
     character: Arc<CharacterSpec>,
     switcher: CharacterSwitcher,
@@ -38,6 +50,7 @@ pub struct Body {
     logs: Logs,
     info: Information,
     stats: Stats,
+    sync: LiveSync,
 }
 
 #[derive(Clone, Debug)]
@@ -45,25 +58,36 @@ pub enum Message {
     Initializing,
 
     CloseButtonPressed(Arc<PlaybookSpec>),
+    AttachButtonPressed,
+    DetachButtonPressed,
+    Attached(Result<()>),
     TabSelected(TabId),
 
     Logs(logs::Message),
     Info(inspect::Message),
     Stats(stats::Message),
+    Sync(sync::Message),
 
     Switcher(character_switcher::Message),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TabId {
     #[default]
     Logs,
     Info,
     Stats,
+    Sync,
 }
 
 impl Body {
-    pub fn new(ctx: Context, playbook: Arc<PlaybookSpec>, character: Arc<CharacterSpec>) -> Self {
+    pub fn new(
+        ctx: Context,
+        supervisor: Supervisor,
+        reporter: Reporter,
+        playbook: Arc<PlaybookSpec>,
+        character: Arc<CharacterSpec>,
+    ) -> Self {
         Self {
             playbook: playbook.clone(),
             character: character.clone(),
@@ -72,9 +96,22 @@ impl Body {
             logs: Logs::new(ctx.clone(), playbook.clone(), character.clone()),
             info: Information::new(ctx.clone(), playbook.clone(), character.clone()),
             stats: Stats::new(ctx.clone(), playbook.clone(), character.clone()),
+            sync: LiveSync::new(supervisor.clone(), playbook.clone(), character.clone()),
+            ctx,
+            supervisor,
+            reporter,
         }
     }
 
+    /// The character shown, which may have been switched.
+    pub fn character(&self) -> &CharacterSpec {
+        &self.character
+    }
+
+    pub fn active_tab(&self) -> TabId {
+        self.active_tab
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Initializing => {
@@ -84,10 +121,41 @@ impl Body {
                 ]);
             }
             Message::CloseButtonPressed(_) => {}
+            Message::AttachButtonPressed => {
+                if let Ok(Some(workspace)) = DialogBuilder::file().open_single_dir().show() {
+                    self.sync.set_error(None);
+                    return Task::perform(
+                        attach(
+                            self.ctx.clone(),
+                            self.supervisor.clone(),
+                            self.reporter.clone(),
+                            self.playbook.id.clone(),
+                            self.character.meta.name.clone(),
+                            workspace,
+                        ),
+                        Message::Attached,
+                    );
+                }
+            }
+            Message::DetachButtonPressed => {
+                detach(
+                    &self.ctx,
+                    &self.supervisor,
+                    &self.playbook.id,
+                    &self.character.meta.name,
+                );
+            }
+            Message::Attached(result) => {
+                if let Err(e) = result {
+                    error!("Failed to attach the local directory: {}", e);
+                    self.sync.set_error(Some(e.to_string()));
+                }
+            }
             Message::TabSelected(tab) => self.active_tab = tab,
             Message::Logs(message) => return self.logs.update(message).map(Message::Logs),
             Message::Info(message) => return self.info.update(message).map(Message::Info),
             Message::Stats(message) => return self.stats.update(message).map(Message::Stats),
+            Message::Sync(message) => return self.sync.update(message).map(Message::Sync),
             Message::Switcher(message) => {
                 let action = self.switcher.update(message);
 
@@ -105,6 +173,7 @@ impl Body {
             self.logs.subscription().map(Message::Logs),
             self.info.subscription().map(Message::Info),
             self.stats.subscription().map(Message::Stats),
+            self.sync.subscription().map(Message::Sync),
         ])
     }
 
@@ -128,6 +197,7 @@ impl Body {
             Row::new()
                 .push(self.header())
                 .push(space::horizontal())
+                .push(self.sync_actions())
                 // .push(self.actions())
                 .width(Length::Fill)
                 .align_y(Alignment::Center),
@@ -145,20 +215,71 @@ impl Body {
             }
         }
 
+        let mut state = Row::new()
+            .push(
+                Text::new("Running")
+                    .size(FONT_SIZE_SMALLER)
+                    .style(styles::text::success),
+            )
+            .spacing(SPACING_SMALL);
+        let status = self.supervisor.status(&self.playbook.id, &self.character.meta.name);
+        if let (None, Some(error)) = (&status, self.sync.error()) {
+            state = state.push(tooltip(
+                Text::new("Attach failed")
+                    .size(FONT_SIZE_SMALLER)
+                    .style(styles::text::danger),
+                Container::new(Text::new(error).size(14))
+                    .padding(8)
+                    .style(styles::container::toolbar),
+                Position::Bottom,
+            ));
+        }
+        if let Some(sync) = status {
+            let style = match sync.state {
+                _ if sync.paused => styles::text::secondary,
+                SyncState::Starting => styles::text::primary,
+                SyncState::Watching => styles::text::success,
+                SyncState::Restarting(_) => styles::text::danger,
+            };
+            let state_text = match sync.paused {
+                true => String::from("Paused"),
+                false => sync.state.to_string(),
+            };
+            state = state.push(
+                Text::new(format!("Live sync: {}", state_text))
+                    .size(FONT_SIZE_SMALLER)
+                    .style(style),
+            );
+        }
+
         items.push(
             Column::new()
                 .push(Text::new(&self.character.meta.name))
-                .push(
-                    Text::new("Running")
-                        .size(FONT_SIZE_SMALLER)
-                        .style(styles::text::success),
-                )
+                .push(state)
                 .into(),
         );
 
         Row::with_children(items).align_y(Alignment::Center).spacing(8).into()
     }
 
+    /// Attach a local directory to the actor to sync it, or detach it.
+    fn sync_actions(&self) -> Element<'_, Message> {
+        let attached = self
+            .supervisor
+            .status(&self.playbook.id, &self.character.meta.name)
+            .is_some();
+        let button = match attached {
+            true => Button::new(Text::new("Detach folder").size(FONT_SIZE_SMALL))
+                .style(styles::button::secondary)
+                .on_press(Message::DetachButtonPressed),
+            false => Button::new(Text::new("Attach folder").size(FONT_SIZE_SMALL))
+                .style(styles::button::primary)
+                .on_press(Message::AttachButtonPressed),
+        };
+
+        button.into()
+    }
+
     #[allow(dead_code)]
     fn actions(&self) -> Element<'_, Message> {
         Row::new()
@@ -184,6 +305,7 @@ impl Body {
                 self.stats.label(),
                 self.stats.content().map(Message::Stats),
             )
+            .push(TabId::Sync, self.sync.label(), self.sync.content().map(Message::Sync))
             .set_active_tab(&self.active_tab)
             .tab_label_padding(8.0)
             .height(Length::Shrink)
@@ -12,19 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use iced::widget::space;
 use iced::{Alignment, Length};
 use iced_aw::Card;
 use native_dialog::DialogBuilder;
 
+use crate::errors::Result;
 use crate::styles::{self, constants::*};
-use crate::widgets::{Button, Checkbox, Column, Container, Element, Row, Scrollable, Text, TextInput};
+use crate::utils::strings::format_bytes;
+use crate::utils::uploader::Preview;
+use crate::widgets::{Button, Checkbox, Column, Container, Element, Radio, Row, Scrollable, Text, TextInput};
+
+/// The number of excluded paths listed in the preview.
+const PREVIEW_EXCLUDED: usize = 10;
 
 #[derive(Default)]
 pub struct Composer {
     form: Form,
+    /// What the upload of the local workspace would send, once loaded.
+    preview: Option<Result<Preview>>,
+    previewing: bool,
+    /// The characters found under the local directory, by name and directory.
+    characters: Option<Result<Vec<(String, PathBuf)>>>,
+    scanning: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +47,12 @@ pub enum Message {
     RepositoryChanged(String),
     SelectFileButtonPressed,
     LiveUpdateChecked(bool),
+    PreviewButtonPressed,
+    PreviewLoaded(Result<Preview>),
+    ScanButtonPressed,
+    CharactersScanned(Result<Vec<(String, PathBuf)>>),
+    /// A character was picked, by its index among the ones found.
+    CharacterSelected(usize),
 
     CancelButtonPressed,
     SubmitButtonPressed,
@@ -43,6 +61,10 @@ pub enum Message {
 pub enum Action {
     None,
     Submit(Form),
+    /// Preview the upload of the given local workspace.
+    Preview(String),
+    /// Find the characters under the given local directory.
+    Scan(String),
     Cancel,
 }
 
@@ -51,12 +73,25 @@ pub struct Form {
     pub title: String,
     pub description: String,
     pub preface: String,
+    /// The directory of the character picked among the ones found, the
+    /// local directory itself holds the character otherwise.
+    pub character: Option<PathBuf>,
     pub live: bool,
 }
 
+impl Form {
+    /// The local directory of the character to compose.
+    pub fn workspace(&self) -> PathBuf {
+        match &self.character {
+            Some(directory) => directory.clone(),
+            None => PathBuf::from(&self.preface),
+        }
+    }
+}
+
 impl Composer {
     pub fn reset(&mut self) {
-        self.form = Form::default();
+        *self = Self::default();
     }
 
     pub fn update(&mut self, message: Message) -> Action {
@@ -71,11 +106,45 @@ impl Composer {
             }
             Message::RepositoryChanged(repository) => {
                 self.form.preface = repository;
+                self.forget();
                 Action::None
             }
             Message::SelectFileButtonPressed => {
                 if let Ok(Some(path)) = DialogBuilder::file().open_single_dir().show() {
                     self.form.preface = path.to_str().unwrap_or_default().to_string();
+                    self.forget();
+                }
+                Action::None
+            }
+            Message::PreviewButtonPressed => {
+                self.previewing = true;
+                Action::Preview(self.form.workspace().to_string_lossy().to_string())
+            }
+            Message::PreviewLoaded(preview) => {
+                self.previewing = false;
+                self.preview = Some(preview);
+                Action::None
+            }
+            Message::ScanButtonPressed => {
+                self.scanning = true;
+                Action::Scan(self.form.preface.clone())
+            }
+            Message::CharactersScanned(characters) => {
+                self.scanning = false;
+                self.form.character = match &characters {
+                    Ok(characters) => characters.first().map(|(_, dir)| dir.clone()),
+                    Err(_) => None,
+                };
+                self.characters = Some(characters);
+                self.preview = None;
+                Action::None
+            }
+            Message::CharacterSelected(index) => {
+                if let Some(Ok(characters)) = &self.characters {
+                    if let Some((_, directory)) = characters.get(index) {
+                        self.form.character = Some(directory.clone());
+                        self.preview = None;
+                    }
                 }
                 Action::None
             }
@@ -88,6 +157,13 @@ impl Composer {
         }
     }
 
+    /// Forget what was learned about the previous local directory.
+    fn forget(&mut self) {
+        self.preview = None;
+        self.characters = None;
+        self.form.character = None;
+    }
+
     pub fn view(&self) -> Element<'_, Message> {
         let title = Text::new("Compose a new playbook").size(FONT_SIZE_LARGE);
         let element = Card::new(title, self.form())
@@ -145,11 +221,100 @@ impl Composer {
                     .on_toggle(Message::LiveUpdateChecked)
                     .into(),
             );
+            fields.push(self.characters());
+            fields.push(self.preview());
         }
 
         Column::with_children(fields).spacing(SPACING_LARGE).into()
     }
 
+    /// The characters found under the local directory, to pick the one to
+    /// develop, e.g. a service of a monorepo.
+    fn characters(&self) -> Element<'_, Message> {
+        let mut button = Button::new(Text::new(match self.scanning {
+            true => "Scanning...",
+            false => "Find characters",
+        }))
+        .style(styles::button::secondary);
+        if !self.scanning {
+            button = button.on_press(Message::ScanButtonPressed);
+        }
+
+        let mut children = vec![button.into()];
+        match &self.characters {
+            None => {}
+            Some(Err(err)) => children.push(Text::new(err.to_string()).style(styles::text::danger).into()),
+            Some(Ok(characters)) if characters.is_empty() => {
+                children.push(Text::new("No characters found").style(styles::text::secondary).into());
+            }
+            Some(Ok(characters)) => {
+                let root = Path::new(&self.form.preface);
+                let selected = characters
+                    .iter()
+                    .position(|(_, directory)| self.form.character.as_ref() == Some(directory));
+                for (index, (name, directory)) in characters.iter().enumerate() {
+                    let relative = directory.strip_prefix(root).unwrap_or(directory);
+                    let label = match relative.as_os_str().is_empty() {
+                        true => name.clone(),
+                        false => format!("{} ({})", name, relative.display()),
+                    };
+                    children.push(Radio::new(label, index, selected, Message::CharacterSelected).into());
+                }
+            }
+        }
+
+        Column::with_children(children).spacing(SPACING_SMALL).into()
+    }
+
+    /// The files the local workspace would upload, and the ones left out.
+    fn preview(&self) -> Element<'_, Message> {
+        let mut button = Button::new(Text::new(match self.previewing {
+            true => "Previewing...",
+            false => "Preview upload",
+        }))
+        .style(styles::button::secondary);
+        if !self.previewing {
+            button = button.on_press(Message::PreviewButtonPressed);
+        }
+
+        let mut children = vec![button.into()];
+        match &self.preview {
+            None => {}
+            Some(Err(err)) => children.push(Text::new(err.to_string()).style(styles::text::danger).into()),
+            Some(Ok(preview)) => {
+                let summary = format!("{} files, {} in total", preview.files, format_bytes(preview.total));
+                children.push(Text::new(summary).into());
+                if let Some(limit) = preview.limit.filter(|_| preview.exceeds_limit()) {
+                    let warning = format!("This exceeds the maximum size of {}", format_bytes(limit));
+                    children.push(Text::new(warning).style(styles::text::danger).into());
+                }
+
+                children.push(Text::new("Largest files").size(FONT_SIZE_SMALL).into());
+                for (name, size) in &preview.largest {
+                    children.push(entry(name.display().to_string(), *size));
+                }
+
+                if !preview.excluded.is_empty() {
+                    children.push(Text::new("Excluded").size(FONT_SIZE_SMALL).into());
+                }
+                for (name, rule, size) in preview.excluded.iter().take(PREVIEW_EXCLUDED) {
+                    children.push(entry(format!("{} by {}", name.display(), rule), *size));
+                }
+                if preview.excluded.len() > PREVIEW_EXCLUDED {
+                    let more = format!("and {} more", preview.excluded.len() - PREVIEW_EXCLUDED);
+                    children.push(
+                        Text::new(more)
+                            .size(FONT_SIZE_SMALLER)
+                            .style(styles::text::secondary)
+                            .into(),
+                    );
+                }
+            }
+        }
+
+        Column::with_children(children).spacing(SPACING_SMALL).into()
+    }
+
     fn actions(&self) -> Element<'_, Message> {
         let cancel_button = Button::new(Text::new("Cancel").style(styles::text::secondary))
             .style(styles::button::text)
@@ -170,3 +335,15 @@ impl Composer {
         .into()
     }
 }
+
+fn entry<'a>(name: String, size: u64) -> Element<'a, Message> {
+    Row::new()
+        .push(Text::new(name).size(FONT_SIZE_SMALLER).width(Length::Fill))
+        .push(
+            Text::new(format_bytes(size))
+                .size(FONT_SIZE_SMALLER)
+                .style(styles::text::secondary),
+        )
+        .spacing(SPACING_SMALL)
+        .into()
+}
@@ -0,0 +1,271 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::config::{Cluster, Configuration};
+use iced::widget::space;
+use iced::{Alignment, Length};
+use iced_aw::Card;
+use native_dialog::{DialogBuilder, MessageLevel};
+use tracing::error;
+
+use crate::errors::{Errors, Result};
+use crate::styles::{self, constants::*};
+use crate::widgets::cluster_form::{self, ClusterForm};
+use crate::widgets::{Button, Column, Container, Element, Row, Scrollable, Text};
+
+/// ContextManager adds, edits and deletes the clusters of the configuration.
+#[derive(Default)]
+pub struct ContextManager {
+    /// The clusters by name, sorted.
+    clusters: Vec<(String, Cluster)>,
+    current: String,
+    /// The cluster being added or edited.
+    editor: Option<Editor>,
+    saving: bool,
+    error: Option<Errors>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    AddButtonPressed,
+    EditButtonPressed(String),
+    DeleteButtonPressed(String),
+
+    Form(cluster_form::Message),
+    SaveButtonPressed,
+    CancelButtonPressed,
+
+    Saved(Result<()>),
+    CloseButtonPressed,
+}
+
+pub enum Action {
+    None,
+    /// Save the cluster by the name, replacing the previous one if any.
+    Save(Option<String>, String, Cluster),
+    Delete(String),
+    /// The configuration was changed, the flag tells whether the current
+    /// context was one of the changes.
+    Changed(bool),
+    Close,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Editor {
+    /// The name of the cluster being edited, none when it's a new one.
+    previous: Option<String>,
+    form: ClusterForm,
+}
+
+impl ContextManager {
+    pub fn new(configuration: &Configuration) -> Self {
+        let mut manager = Self::default();
+        manager.load(configuration);
+        manager
+    }
+
+    /// Load the clusters of the configuration, and leave the editor.
+    pub fn load(&mut self, configuration: &Configuration) {
+        self.refresh(configuration);
+        self.editor = None;
+    }
+
+    /// Refresh the clusters of the configuration, the editor is kept.
+    pub fn refresh(&mut self, configuration: &Configuration) {
+        self.clusters = vec![];
+        self.current = String::new();
+        if let Some(context) = &configuration.context {
+            self.clusters = context.clusters().clone().into_iter().collect();
+            self.current = context.current().map(|(name, _)| name).unwrap_or_default();
+        }
+        self.clusters.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    pub fn update(&mut self, message: Message) -> Action {
+        match message {
+            Message::AddButtonPressed => {
+                self.error = None;
+                self.editor = Some(Editor::default());
+            }
+            Message::EditButtonPressed(name) => {
+                self.error = None;
+                if let Some((_, cluster)) = self.clusters.iter().find(|(n, _)| n == &name) {
+                    self.editor = Some(Editor {
+                        previous: Some(name.clone()),
+                        form: ClusterForm::new(name, cluster),
+                    });
+                }
+            }
+            Message::DeleteButtonPressed(name) => {
+                self.error = None;
+                let confirmed = DialogBuilder::message()
+                    .set_level(MessageLevel::Warning)
+                    .set_title("Delete context")
+                    .set_text(format!(
+                        "Are you sure you want to delete the context {}? It will be removed from the amp configuration.",
+                        name
+                    ))
+                    .confirm()
+                    .show();
+
+                match confirmed {
+                    Ok(true) => {
+                        self.saving = true;
+                        return Action::Delete(name);
+                    }
+                    Ok(false) => {}
+                    Err(err) => error!("Failed to show the delete dialog: {:?}", err),
+                }
+            }
+
+            Message::Form(message) => self.edit(|editor| editor.form.update(message)),
+            Message::SaveButtonPressed => {
+                if let Some(editor) = &self.editor {
+                    self.saving = true;
+                    self.error = None;
+
+                    let (name, cluster) = editor.form.cluster();
+                    return Action::Save(editor.previous.clone(), name, cluster);
+                }
+            }
+            Message::CancelButtonPressed => {
+                self.error = None;
+                self.editor = None;
+            }
+
+            Message::Saved(result) => {
+                self.saving = false;
+                match result {
+                    Ok(()) => {
+                        // Deleting never touches the current context.
+                        let current = self
+                            .editor
+                            .as_ref()
+                            .is_some_and(|editor| editor.previous.as_ref() == Some(&self.current));
+                        return Action::Changed(current);
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            Message::CloseButtonPressed => return Action::Close,
+        }
+
+        Action::None
+    }
+
+    fn edit(&mut self, f: impl FnOnce(&mut Editor)) {
+        if let Some(editor) = &mut self.editor {
+            f(editor);
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let title = Text::new("Manage contexts").size(FONT_SIZE_LARGE);
+        let body = match &self.editor {
+            Some(editor) => self.editor(editor),
+            None => self.clusters(),
+        };
+        let element = Card::new(title, body)
+            .close_size(ICON_FONT_SIZE_TOOLBAR)
+            .on_close(Message::CloseButtonPressed)
+            .foot(self.actions())
+            .padding(SPACING_LARGE.into());
+
+        let content = Scrollable::new(element);
+        Container::new(Column::new().push(content).max_width(480))
+            .center(Length::Fill)
+            .into()
+    }
+
+    fn clusters(&self) -> Element<'_, Message> {
+        let mut children = vec![];
+        for (name, cluster) in &self.clusters {
+            let mut title = cluster.title.clone();
+            if name == &self.current {
+                title = format!("{} (current)", title);
+            }
+            let details = Column::new()
+                .push(Text::new(title))
+                .push(
+                    Text::new(format!("{} · {}", name, cluster.server))
+                        .size(FONT_SIZE_SMALLER)
+                        .style(styles::text::secondary),
+                )
+                .width(Length::Fill);
+
+            let mut delete = Button::new(Text::new("Delete")).style(styles::button::danger);
+            if !self.saving && name != &self.current {
+                delete = delete.on_press(Message::DeleteButtonPressed(name.clone()));
+            }
+            let edit = Button::new(Text::new("Edit"))
+                .style(styles::button::secondary)
+                .on_press(Message::EditButtonPressed(name.clone()));
+
+            children.push(
+                Row::new()
+                    .push(details)
+                    .push(edit)
+                    .push(delete)
+                    .align_y(Alignment::Center)
+                    .spacing(SPACING_SMALL)
+                    .into(),
+            );
+        }
+        if let Some(error) = &self.error {
+            children.push(Text::new(error.to_string()).style(styles::text::danger).into());
+        }
+
+        Column::with_children(children).spacing(SPACING_NORMAL).into()
+    }
+
+    fn editor<'a>(&'a self, editor: &'a Editor) -> Element<'a, Message> {
+        let mut fields = vec![editor.form.view().map(Message::Form)];
+        if let Some(error) = &self.error {
+            fields.push(Text::new(error.to_string()).style(styles::text::danger).into());
+        }
+
+        Column::with_children(fields).spacing(SPACING_LARGE).into()
+    }
+
+    fn actions(&self) -> Element<'_, Message> {
+        let content = match &self.editor {
+            None => Row::new().push(space::horizontal()).push(
+                Button::new(Text::new("Add context"))
+                    .style(styles::button::primary)
+                    .on_press(Message::AddButtonPressed),
+            ),
+            Some(editor) => {
+                let cancel_button = Button::new(Text::new("Cancel").style(styles::text::secondary))
+                    .style(styles::button::text)
+                    .on_press(Message::CancelButtonPressed);
+                let mut save_button = Button::new(Text::new(match self.saving {
+                    true => "Connecting...",
+                    false => "Save",
+                }))
+                .style(styles::button::primary)
+                .width(Length::FillPortion(3));
+                if !self.saving && editor.form.is_complete() {
+                    save_button = save_button.on_press(Message::SaveButtonPressed);
+                }
+
+                Row::new()
+                    .push(cancel_button)
+                    .push(space::horizontal())
+                    .push(save_button)
+            }
+        };
+
+        Container::new(content.width(Length::Fill).align_y(Alignment::Center)).into()
+    }
+}
@@ -121,6 +121,8 @@ impl Tab for Logs {
 
 struct Receiver {
     es: reqwest_eventsource::EventSource,
+    /// The context of the stream, so it's restarted on switching.
+    context: String,
     pid: String,
     name: String,
 }
@@ -129,6 +131,7 @@ impl Receiver {
     pub fn new(ctx: Context, pid: &str, name: &str) -> Self {
         Self {
             es: ctx.client().actors().logs(pid, name),
+            context: ctx.name(),
             pid: String::from(pid),
             name: String::from(name),
         }
@@ -142,6 +145,7 @@ impl subscription::Recipe for Receiver {
 
     fn hash(&self, state: &mut Hasher) {
         std::any::TypeId::of::<Self>().hash(state);
+        self.context.hash(state);
         self.pid.hash(state);
         self.name.hash(state);
     }
@@ -15,3 +15,4 @@
 pub mod inspect;
 pub mod logs;
 pub mod stats;
+pub mod sync;
@@ -0,0 +1,196 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::{Alignment, Length, Subscription, Task};
+use iced_aw::TabLabel;
+
+use amp_common::resource::{CharacterSpec, PlaybookSpec};
+
+use crate::styles::{self, constants::*};
+use crate::utils::supervisor::{Supervisor, SyncState, SyncStatus};
+use crate::widgets::empty::empty;
+use crate::widgets::tabs::Tab;
+use crate::widgets::{rule, Button, Column, Element, Row, Scrollable, Text};
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Refresh,
+    PauseButtonPressed,
+    ResumeButtonPressed,
+    ResyncButtonPressed,
+}
+
+pub struct LiveSync {
+    supervisor: Supervisor,
+    status: Option<SyncStatus>,
+    /// Why the last attempt to attach a local folder failed.
+    error: Option<String>,
+    playbook: Arc<PlaybookSpec>,
+    character: Arc<CharacterSpec>,
+}
+
+impl LiveSync {
+    pub fn new(supervisor: Supervisor, playbook: Arc<PlaybookSpec>, character: Arc<CharacterSpec>) -> Self {
+        let status = supervisor.status(&playbook.id, &character.meta.name);
+        Self {
+            supervisor,
+            status,
+            error: None,
+            playbook,
+            character,
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let pid = &self.playbook.id;
+        let name = &self.character.meta.name;
+        match message {
+            Message::Refresh => {}
+            Message::PauseButtonPressed => self.supervisor.pause(pid, name),
+            Message::ResumeButtonPressed => self.supervisor.resume(pid, name),
+            Message::ResyncButtonPressed => self.supervisor.resync(pid, name),
+        }
+        self.status = self.supervisor.status(pid, name);
+
+        Task::none()
+    }
+
+    /// Why the last attempt to attach a local folder failed, if it did.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Show why attaching a local folder failed, or clear it.
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        iced::time::every(Duration::from_secs(1)).map(|_| Message::Refresh)
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let Some(status) = &self.status else {
+            if let Some(error) = &self.error {
+                return empty("Failed to attach the local folder", Some(error.as_str()));
+            }
+            return empty(
+                "Live sync is not enabled",
+                Some("Attach a local folder to the character to sync your changes as you go"),
+            );
+        };
+
+        let state = match &status.state {
+            _ if status.paused => String::from("Paused"),
+            SyncState::Restarting(reason) => format!("{} ({})", status.state, reason),
+            state => state.to_string(),
+        };
+        let last_synced = match status.last_synced {
+            Some(time) => format!("{}s ago", time.elapsed().as_secs()),
+            None => String::from("Never"),
+        };
+
+        let mut children = vec![
+            self.actions(status),
+            self.field("State", state),
+            self.field("Last synced", last_synced),
+            self.field("Pending changes", status.pending.to_string()),
+        ];
+
+        children.push(Text::new("RECENT").size(24).into());
+        if status.recent.is_empty() {
+            children.push(
+                Text::new("Nothing synced yet")
+                    .size(14)
+                    .style(styles::text::secondary)
+                    .into(),
+            );
+        }
+        for path in &status.recent {
+            children.push(Text::new(path).size(14).into());
+        }
+
+        children.push(Text::new("ERRORS").size(24).into());
+        if status.errors.is_empty() {
+            children.push(Text::new("No errors").size(14).style(styles::text::secondary).into());
+        }
+        for err in &status.errors {
+            children.push(Text::new(err).size(14).style(styles::text::danger).into());
+        }
+
+        let content = Column::with_children(children)
+            .padding(16)
+            .spacing(SPACING_NORMAL)
+            .width(Length::Fill);
+        Scrollable::new(content).into()
+    }
+}
+
+impl LiveSync {
+    fn actions(&self, status: &SyncStatus) -> Element<'_, Message> {
+        let toggle = match status.paused {
+            true => Button::new(Text::new("Resume"))
+                .style(styles::button::primary)
+                .on_press(Message::ResumeButtonPressed),
+            false => Button::new(Text::new("Pause"))
+                .style(styles::button::secondary)
+                .on_press(Message::PauseButtonPressed),
+        };
+
+        Row::new()
+            .push(toggle)
+            .push(
+                Button::new(Text::new("Full resync"))
+                    .style(styles::button::secondary)
+                    .on_press(Message::ResyncButtonPressed),
+            )
+            .align_y(Alignment::Center)
+            .spacing(SPACING_SMALL)
+            .into()
+    }
+
+    fn field(&self, label: &str, value: String) -> Element<'_, Message> {
+        Column::new()
+            .push(
+                Row::new()
+                    .push(Text::new(label.to_string()).size(16).width(Length::FillPortion(4)))
+                    .push(Text::new(value).size(14).width(Length::FillPortion(6)))
+                    .width(Length::Fill),
+            )
+            .push(rule::horizontal(1))
+            .width(Length::Fill)
+            .spacing(SPACING_NORMAL)
+            .into()
+    }
+}
+
+impl Tab for LiveSync {
+    type Message = Message;
+
+    fn title(&self) -> String {
+        String::from("Sync")
+    }
+
+    fn label(&self) -> TabLabel {
+        TabLabel::Text(self.title())
+    }
+
+    #[inline]
+    fn view(&self) -> Element<'_, Self::Message> {
+        self.view()
+    }
+}
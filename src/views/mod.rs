@@ -15,5 +15,7 @@
 pub mod body;
 pub mod cast;
 pub mod composer;
+pub mod contexts;
 pub mod detail;
+pub mod onboarding;
 pub mod sidebar;
@@ -0,0 +1,136 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::config::Cluster;
+use iced::widget::space;
+use iced::{Alignment, Length};
+use iced_aw::Card;
+
+use crate::errors::{Errors, Result};
+use crate::styles::{self, constants::*};
+use crate::widgets::cluster_form::{self, ClusterForm};
+use crate::widgets::{Button, Column, Container, Element, Row, Scrollable, Text};
+
+/// Onboarding helps to add the first cluster, when there is no usable
+/// configuration to start with.
+pub struct Onboarding {
+    /// Why the configuration couldn't be used.
+    reason: Errors,
+    form: ClusterForm,
+    saving: bool,
+    error: Option<Errors>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Form(cluster_form::Message),
+    SubmitButtonPressed,
+    Saved(Result<()>),
+}
+
+pub enum Action {
+    None,
+    /// Add the cluster by the given name.
+    Submit(String, Cluster),
+    /// The cluster has been saved as the current context.
+    Ready,
+}
+
+impl Onboarding {
+    pub fn new(reason: Errors) -> Self {
+        Self {
+            reason,
+            form: ClusterForm::default(),
+            saving: false,
+            error: None,
+        }
+    }
+
+    /// The context was saved, but still can't be used.
+    pub fn failed(&mut self, error: Errors) {
+        self.error = Some(error);
+    }
+
+    pub fn update(&mut self, message: Message) -> Action {
+        match message {
+            Message::Form(message) => self.form.update(message),
+            Message::SubmitButtonPressed => {
+                self.saving = true;
+                self.error = None;
+
+                let (name, cluster) = self.form.cluster();
+                return Action::Submit(name, cluster);
+            }
+            Message::Saved(result) => {
+                self.saving = false;
+                match result {
+                    Ok(()) => return Action::Ready,
+                    Err(err) => self.error = Some(err),
+                }
+            }
+        }
+
+        Action::None
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let title = Text::new("Welcome to Amphitheatre").size(FONT_SIZE_LARGE);
+        let element = Card::new(title, self.form())
+            .foot(self.actions())
+            .padding(SPACING_LARGE.into());
+
+        let content = Scrollable::new(element);
+        Container::new(Column::new().push(content).max_width(480))
+            .center(Length::Fill)
+            .into()
+    }
+
+    fn form(&self) -> Element<'_, Message> {
+        let reason = Text::new(self.reason.to_string()).style(styles::text::danger).into();
+        let help = Text::new(
+            "Add the cluster you are working with to get started, \
+            ask your administrator for its server and token if you don't have them.",
+        )
+        .style(styles::text::secondary)
+        .into();
+
+        let mut fields = vec![reason, help, self.form.view().map(Message::Form)];
+        if let Some(error) = &self.error {
+            fields.push(Text::new(error.to_string()).style(styles::text::danger).into());
+        }
+
+        Column::with_children(fields).spacing(SPACING_LARGE).into()
+    }
+
+    fn actions(&self) -> Element<'_, Message> {
+        let mut submit_button = Button::new(Text::new(match self.saving {
+            true => "Connecting...",
+            false => "Add cluster",
+        }))
+        .style(styles::button::primary)
+        .width(Length::FillPortion(3));
+        if !self.saving && self.form.is_complete() {
+            submit_button = submit_button.on_press(Message::SubmitButtonPressed);
+        }
+
+        Container::new(
+            Row::new()
+                .push(space::horizontal())
+                .push(submit_button)
+                .width(Length::Fill)
+                .align_y(Alignment::Center),
+        )
+        .into()
+    }
+}
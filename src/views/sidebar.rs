@@ -12,33 +12,69 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::time::Duration;
+use std::cmp::Reverse;
+use std::hash::Hash;
 use tracing::{debug, error};
 
-use iced::{Alignment, Length, Subscription, Task};
+use futures::StreamExt;
+use iced::font::Weight;
+use iced::keyboard::{self, key::Named, Key};
+use iced::{Alignment, Font, Length, Subscription, Task};
 use iced_fonts::bootstrap;
+use iced_futures::subscription::{self, from_recipe, Hasher};
+use iced_futures::BoxStream;
+use native_dialog::{DialogBuilder, MessageLevel};
 
-use crate::cmd::config::switch_context;
-use crate::cmd::playbook::{compose, refresh_playbooks};
+use crate::cmd::config::{delete_context, save_context, switch_context};
+use crate::cmd::playbook::{compose, forget, listings, preview, refresh_playbooks, resumable, resume, scan, Listing};
 use crate::context::Context;
-use crate::errors::Result;
+use crate::errors::{Errors, Result};
 use crate::styles::{self, constants::*};
-use crate::utils::connection_status::ConnectionStatus;
+use crate::utils::config_watcher;
+use crate::utils::connection_status::{self, ConnectionStatus};
+use crate::utils::playbook_watcher::{self, PlaybookEvent};
+use crate::utils::progress::{Progress, Reporter};
+use crate::utils::strings::fuzzy_match;
+use crate::utils::supervisor::Supervisor;
 use crate::widgets::context_switcher::{self, *};
-use crate::widgets::{Button, Column, Container, Element, Row, Scrollable, Text, TextInput};
+use crate::widgets::{Button, Checkbox, Column, Container, Element, ProgressBar, Row, Scrollable, Text, TextInput};
+use amp_common::config::Configuration;
 use amp_common::resource::PlaybookSpec;
 
 use super::composer::{self, Composer};
+use super::contexts::{self, ContextManager};
+
+/// The score added to the matches of the title, over the other fields.
+const TITLE_BONUS: i64 = 5;
+
+/// The title and the connection status of a cluster, over its playbooks.
+type Header<'a> = (String, &'a ConnectionStatus);
 
 pub struct Sidebar {
     ctx: Context,
+    supervisor: Supervisor,
     query: String,
+    /// The result highlighted with the keyboard.
+    cursor: Option<usize>,
     playbooks: Vec<PlaybookSpec>,
     status: ConnectionStatus,
+    /// List the playbooks of every cluster, not only the current one's.
+    all: bool,
+    /// The other clusters, when listing every cluster.
+    listings: Vec<Listing>,
     show_modal: bool,
     selected_playbook: Option<PlaybookSpec>,
     switcher: ContextSwitcher,
     composer: Composer,
+    contexts: Option<ContextManager>,
+    /// Something the user should know about, e.g. the current context was
+    /// changed outside of the desktop.
+    notice: Option<String>,
+    reporter: Reporter,
+    progress: Option<Progress>,
+    /// The saved live-sync sessions of the current context were offered
+    /// to be resumed.
+    offered: bool,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -47,33 +83,59 @@ pub enum Message {
     Initializing,
     RefreshPlaybooks(Result<()>),
     PlaybooksLoaded(Result<Vec<PlaybookSpec>>),
+    PlaybookChanged(PlaybookEvent),
+    /// The playbooks of another cluster changed, by its context name.
+    ListingChanged(String, PlaybookEvent),
+    ListingStatusChanged(String, ConnectionStatus),
+    AllContextsToggled(bool),
 
     CreateButtonPressed,
     TextInputChanged(String),
+    CursorMoved(isize),
+    SearchSubmitted,
+    SearchCleared,
     PlaybookSelected(Option<Result<PlaybookSpec>>),
+    /// A playbook of another cluster was selected, by its context name.
+    ContextPlaybookSelected(String, PlaybookSpec),
+
+    UploadProgressed(Option<Progress>),
 
     Switcher(context_switcher::Message),
     Composer(composer::Message),
+    Contexts(contexts::Message),
+    ModalClosed,
+
+    ConfigurationChanged,
+    /// The current context was switched or edited, everything shown for
+    /// the previous one is gone.
+    ContextChanged(Result<()>),
+    ConnectionChanged(ConnectionStatus),
+    NoticeDismissed,
 }
 
 impl Sidebar {
-    pub fn new(ctx: Context) -> Self {
-        let config = ctx.configuration();
-        let context = config.context.as_ref().unwrap();
-        let (name, cluster) = context.current().unwrap_or_default();
+    pub fn new(ctx: Context, supervisor: Supervisor, reporter: Reporter) -> Self {
         let status = ConnectionStatus::default();
-
-        let switcher = ContextSwitcher::new(name, cluster.title, context.clusters().clone(), status.clone());
+        let switcher = switcher(&ctx, &status);
 
         Self {
             ctx,
+            supervisor,
             query: String::new(),
+            cursor: None,
             playbooks: vec![],
             status,
+            all: false,
+            listings: vec![],
             show_modal: false,
             selected_playbook: None,
             switcher,
             composer: Composer::default(),
+            contexts: None,
+            notice: None,
+            reporter,
+            progress: None,
+            offered: false,
         }
     }
 
@@ -84,7 +146,7 @@ impl Sidebar {
                 return Task::perform(refresh_playbooks(self.ctx.clone()), Message::PlaybooksLoaded);
             }
             Message::RefreshPlaybooks(arg) => match arg {
-                Ok(_) => return Task::perform(refresh_playbooks(self.ctx.clone()), Message::PlaybooksLoaded),
+                Ok(_) => return self.refresh(),
                 Err(e) => {
                     error!("Failed to refresh playbooks: {}", e);
                 }
@@ -96,22 +158,105 @@ impl Sidebar {
                         playbooks.iter().map(|p| p.id.clone()).collect::<Vec<_>>()
                     );
                     self.playbooks = playbooks;
-                    self.status = ConnectionStatus::Connected;
+
+                    if !self.offered {
+                        self.offered = true;
+                        return self.resume_sessions();
+                    }
                 }
                 Err(e) => {
                     error!("Failed to load playbooks: {}", e);
                     self.playbooks = vec![];
-                    self.status = ConnectionStatus::Disconnected;
                 }
             },
 
+            Message::PlaybookChanged(event) => match event {
+                PlaybookEvent::Ready => {
+                    if !self.offered {
+                        self.offered = true;
+                        return self.resume_sessions();
+                    }
+                }
+                PlaybookEvent::Failed(e) => error!("Failed to watch playbooks: {}", e),
+                event => {
+                    debug!("Playbook changed: {:?}", event);
+                    if let PlaybookEvent::Updated(playbook) = &event {
+                        if self.selected_playbook.as_ref().is_some_and(|p| p.id == playbook.id) {
+                            self.selected_playbook = Some(playbook.clone());
+                        }
+                    }
+                    if let PlaybookEvent::Removed(pid) = &event {
+                        // It was deleted elsewhere, so there is nothing left to sync.
+                        self.supervisor.stop_playbook(pid);
+                        forget(&self.ctx, pid);
+                    }
+                    playbook_watcher::apply(&mut self.playbooks, event);
+                }
+            },
+
+            Message::ListingChanged(name, event) => {
+                let Some(listing) = self.listings.iter_mut().find(|l| l.context == name) else {
+                    return Task::none();
+                };
+                match event {
+                    PlaybookEvent::Failed(e) => error!("Failed to watch the playbooks of {}: {}", name, e),
+                    event => {
+                        if let PlaybookEvent::Updated(playbook) = &event {
+                            if self.selected_playbook.as_ref().is_some_and(|p| p.id == playbook.id) {
+                                self.selected_playbook = Some(playbook.clone());
+                            }
+                        }
+                        playbook_watcher::apply(&mut listing.playbooks, event);
+                    }
+                }
+            }
+            Message::ListingStatusChanged(name, status) => {
+                if let Some(listing) = self.listings.iter_mut().find(|l| l.context == name) {
+                    listing.status = status;
+                }
+            }
+            Message::AllContextsToggled(all) => {
+                self.all = all;
+                self.relist();
+            }
+
+            Message::UploadProgressed(progress) => self.progress = progress,
+
             Message::CreateButtonPressed => self.show_modal = true,
-            Message::TextInputChanged(query) => self.query = query,
+            Message::TextInputChanged(query) => {
+                self.cursor = Some(0).filter(|_| !query.trim().is_empty());
+                self.query = query;
+            }
+            Message::CursorMoved(delta) => {
+                let count = self.results().iter().map(|(_, hits)| hits.len()).sum::<usize>();
+                if count > 0 {
+                    let cursor = match self.cursor {
+                        Some(cursor) => cursor.saturating_add_signed(delta).min(count - 1),
+                        None if delta < 0 => count - 1,
+                        None => 0,
+                    };
+                    self.cursor = Some(cursor);
+                }
+            }
+            Message::SearchSubmitted => {
+                let hit = self
+                    .cursor
+                    .and_then(|cursor| self.results().into_iter().flat_map(|(_, hits)| hits).nth(cursor));
+                if let Some(hit) = hit {
+                    return Task::done(hit.message());
+                }
+            }
+            Message::SearchCleared => {
+                self.query = String::new();
+                self.cursor = None;
+            }
             Message::PlaybookSelected(result) => {
                 if let Some(result) = result {
                     match result {
                         Ok(playbook) => {
                             debug!("Playbook selected: {:?}", playbook);
+                            // A composed playbook shows up before it's listed.
+                            playbook_watcher::apply(&mut self.playbooks, PlaybookEvent::Added(playbook.clone()));
                             self.selected_playbook = Some(playbook);
                         }
                         Err(e) => {
@@ -121,15 +266,21 @@ impl Sidebar {
                     }
                 }
             }
+            Message::ContextPlaybookSelected(name, playbook) => {
+                debug!("Playbook of {} selected: {:?}", name, playbook);
+                self.selected_playbook = Some(playbook);
+            }
             Message::Switcher(message) => {
                 let action = self.switcher.update(message);
 
                 match action {
                     Action::None => {}
                     Action::Switch(name) => {
-                        debug!("The current context was changed: {:?}", name);
-                        self.selected_playbook = None;
-                        return Task::perform(switch_context(self.ctx.clone(), name), Message::RefreshPlaybooks);
+                        debug!("Switching the current context to {:?}", name);
+                        return Task::perform(switch_context(self.ctx.clone(), name), Message::ContextChanged);
+                    }
+                    Action::Manage => {
+                        self.contexts = Some(ContextManager::new(&self.ctx.configuration()));
                     }
                 }
             }
@@ -143,45 +294,159 @@ impl Sidebar {
                         self.show_modal = false;
 
                         return Task::perform(
-                            compose(self.ctx.clone(), form.title, form.description, form.preface, form.live),
+                            compose(
+                                self.ctx.clone(),
+                                self.supervisor.clone(),
+                                self.reporter.clone(),
+                                form.title,
+                                form.description,
+                                form.preface,
+                                form.character,
+                                form.live,
+                            ),
                             |p| Message::PlaybookSelected(Some(p)),
                         );
                     }
+                    composer::Action::Preview(workspace) => {
+                        return Task::perform(preview(self.ctx.clone(), workspace), |p| {
+                            Message::Composer(composer::Message::PreviewLoaded(p))
+                        });
+                    }
+                    composer::Action::Scan(workspace) => {
+                        return Task::perform(scan(workspace), |c| {
+                            Message::Composer(composer::Message::CharactersScanned(c))
+                        });
+                    }
                     composer::Action::Cancel => {
                         self.show_modal = false;
                         self.composer.reset();
                     }
                 }
             }
+            Message::Contexts(message) => {
+                let Some(manager) = &mut self.contexts else {
+                    return Task::none();
+                };
+
+                match manager.update(message) {
+                    contexts::Action::None => {}
+                    contexts::Action::Save(previous, name, cluster) => {
+                        return Task::perform(save_context(self.ctx.clone(), previous, name, cluster), |r| {
+                            Message::Contexts(contexts::Message::Saved(r))
+                        });
+                    }
+                    contexts::Action::Delete(name) => {
+                        return Task::perform(delete_context(self.ctx.clone(), name), |r| {
+                            Message::Contexts(contexts::Message::Saved(r))
+                        });
+                    }
+                    contexts::Action::Changed(current) => {
+                        manager.load(&self.ctx.configuration());
+                        self.switcher = switcher(&self.ctx, &self.status);
+                        if current {
+                            debug!("The current context was edited: {}", self.ctx.name());
+                            return Task::done(Message::ContextChanged(Ok(())));
+                        }
+                    }
+                    contexts::Action::Close => self.contexts = None,
+                }
+            }
+            Message::ConfigurationChanged => {
+                let previous = self.ctx.name();
+                if let Err(err) = self.ctx.reload() {
+                    error!("Failed to reload the configuration: {}", err);
+                    self.notice = Some(format!("The amp configuration can't be reloaded: {}", err));
+                    return Task::none();
+                }
+                debug!("The configuration was reloaded");
+
+                self.switcher = switcher(&self.ctx, &self.status);
+                if let Some(manager) = &mut self.contexts {
+                    manager.refresh(&self.ctx.configuration());
+                }
+                self.relist();
+
+                let current = self.ctx.name();
+                if current != previous {
+                    self.notice = Some(format!(
+                        "The current context was changed from {} to {} outside of the desktop",
+                        previous, current
+                    ));
+                    return Task::done(Message::ContextChanged(Ok(())));
+                }
+            }
+            Message::ContextChanged(result) => match result {
+                Ok(()) => {
+                    debug!("The current context was changed: {}", self.ctx.name());
+                    self.leave_context();
+                    self.switcher = switcher(&self.ctx, &self.status);
+                    self.relist();
+                    return Task::perform(refresh_playbooks(self.ctx.clone()), Message::PlaybooksLoaded);
+                }
+                Err(e) => error!("Failed to switch the context: {}", e),
+            },
+            Message::ConnectionChanged(status) => {
+                debug!("The connection status changed: {}", status);
+                self.status = status.clone();
+                self.switcher.set_status(status);
+            }
+            Message::NoticeDismissed => self.notice = None,
+            Message::ModalClosed => {
+                self.show_modal = false;
+                self.contexts = None;
+            }
         };
 
         Task::none()
     }
 
-    /// poll playbooks from the server every 5 seconds
+    /// watch the playbooks of the cluster, and of the other clusters when
+    /// listing every cluster, and follow the uploads, the configuration
+    /// file and the connections to the clusters
     pub fn subscription(&self) -> Subscription<Message> {
-        iced::time::every(Duration::from_secs(5)).map(|_| Message::RefreshPlaybooks(Ok(())))
+        let mut subscriptions = vec![
+            from_recipe(Playbooks(self.ctx.clone())),
+            from_recipe(Uploads(self.reporter.subscribe())),
+            from_recipe(ConfigurationFile),
+            from_recipe(Connection(self.ctx.clone())),
+            match self.contexts.is_none() && !self.show_modal {
+                true => keyboard::listen().filter_map(navigate),
+                false => Subscription::none(),
+            },
+        ];
+        for listing in &self.listings {
+            subscriptions.push(from_recipe(ListingPlaybooks(listing.ctx.clone())));
+            subscriptions.push(from_recipe(ListingConnection(listing.ctx.clone())));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    /// Get the context of another listed cluster, by its name.
+    pub fn cluster(&self, name: &str) -> Option<Context> {
+        self.listings.iter().find(|l| l.context == name).map(|l| l.ctx.clone())
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        let selected_playbook_id = self.selected_playbook.as_ref().map(|p| &p.id);
-        let playbooks = Scrollable::new(self.playbooks.iter().fold(
-            Column::new().width(Length::Fill).spacing(SPACING_NORMAL),
-            |column, playbook| {
-                let active = Some(&playbook.id) == selected_playbook_id;
-                column.push(playbook_item(playbook, active))
-            },
-        ))
-        .width(Length::Fill)
-        .height(Length::Shrink);
+        let playbooks = Scrollable::new(self.playbooks())
+            .width(Length::Fill)
+            .height(Length::Shrink);
 
-        let content = Column::new()
-            .push(self.switcher.view().map(Message::Switcher))
+        let mut content = Column::new().push(self.switcher.view().map(Message::Switcher));
+        if let Some(notice) = &self.notice {
+            content = content.push(notice_bar(notice));
+        }
+        content = content
             .push(self.omnibox())
-            .push(playbooks)
-            .padding(16)
-            .spacing(16)
-            .height(Length::Fill);
+            .push(
+                Checkbox::new(self.all)
+                    .label("All contexts")
+                    .on_toggle(Message::AllContextsToggled),
+            )
+            .push(playbooks);
+        if let Some(progress) = &self.progress {
+            content = content.push(upload_progress(progress));
+        }
+        let content = content.padding(16).spacing(16).height(Length::Fill);
 
         Container::new(content)
             .style(styles::container::sidebar)
@@ -192,49 +457,471 @@ impl Sidebar {
 }
 
 impl Sidebar {
+    /// Offer to resume the live-sync sessions saved in the last run.
+    fn resume_sessions(&self) -> Task<Message> {
+        let sessions: Vec<_> = resumable(&self.ctx, &self.playbooks)
+            .into_iter()
+            .filter(|s| self.supervisor.status(&s.pid, &s.actor).is_none())
+            .collect();
+        if sessions.is_empty() {
+            return Task::none();
+        }
+
+        let actors: Vec<_> = sessions
+            .iter()
+            .map(|s| format!("{} ({})", s.actor, s.workspace.display()))
+            .collect();
+        let confirmed = DialogBuilder::message()
+            .set_level(MessageLevel::Info)
+            .set_title("Resume live sync")
+            .set_text(format!(
+                "The following workspaces were synced in the last session:\n\n{}\n\nDo you want to catch up with the changes and keep watching them?",
+                actors.join("\n")
+            ))
+            .confirm()
+            .show();
+
+        match confirmed {
+            Ok(true) => {}
+            Ok(false) => {
+                for session in &sessions {
+                    forget(&self.ctx, &session.pid);
+                }
+                return Task::none();
+            }
+            Err(err) => {
+                error!("Failed to show the resume dialog: {:?}", err);
+                return Task::none();
+            }
+        }
+
+        Task::perform(
+            resume(
+                self.ctx.clone(),
+                self.supervisor.clone(),
+                self.reporter.clone(),
+                sessions,
+            ),
+            |_| Message::RefreshPlaybooks(Ok(())),
+        )
+    }
+
+    /// Refresh the playbooks of the current cluster, the other clusters
+    /// are kept up to date by their watchers.
+    fn refresh(&self) -> Task<Message> {
+        Task::perform(refresh_playbooks(self.ctx.clone()), Message::PlaybooksLoaded)
+    }
+
+    /// List the other clusters when listing every cluster, keeping the
+    /// listings of the clusters that didn't change.
+    fn relist(&mut self) {
+        let previous = std::mem::take(&mut self.listings);
+        if self.all {
+            self.listings = listings(&self.ctx, previous);
+        }
+    }
+
+    /// The playbooks matching the search and the filter, grouped by cluster
+    /// when listing every cluster, and ranked by relevance when searching.
+    fn results(&self) -> Vec<(Option<Header<'_>>, Vec<Hit<'_>>)> {
+        let mut groups = vec![(None, &self.playbooks, None)];
+        if self.all {
+            let configuration = self.ctx.configuration();
+            let title = configuration
+                .context
+                .as_ref()
+                .and_then(|c| c.current())
+                .map(|(_, c)| c.title);
+            groups[0].0 = Some((title.unwrap_or_default(), &self.status));
+            groups.extend(self.listings.iter().map(|listing| {
+                let header = (listing.title.clone(), &listing.status);
+                (Some(header), &listing.playbooks, Some(&listing.context))
+            }));
+        }
+
+        groups
+            .into_iter()
+            .map(|(listing, playbooks, context)| {
+                let mut hits: Vec<_> = playbooks
+                    .iter()
+                    .filter_map(|playbook| Hit::new(&self.query, context, playbook))
+                    .collect();
+                if !self.query.trim().is_empty() {
+                    hits.sort_by_key(|hit| Reverse(hit.score));
+                }
+                (listing, hits)
+            })
+            .collect()
+    }
+
+    fn playbooks(&self) -> Column<'_, Message> {
+        let selected_playbook_id = self.selected_playbook.as_ref().map(|p| &p.id);
+
+        let mut index = 0;
+        let mut column = Column::new().width(Length::Fill).spacing(SPACING_NORMAL);
+        for (listing, hits) in self.results() {
+            if let Some((title, status)) = listing {
+                column = column.push(listing_header(title, status));
+                if let Some(error) = status.error() {
+                    column = column.push(Text::new(error).size(FONT_SIZE_SMALLER).style(styles::text::danger));
+                }
+            }
+            for hit in hits {
+                let active = Some(&hit.playbook.id) == selected_playbook_id;
+                column = column.push(playbook_item(hit, active, self.cursor == Some(index)));
+                index += 1;
+            }
+        }
+        if index == 0 && !self.query.trim().is_empty() {
+            column = column.push(
+                Text::new("No playbooks match")
+                    .size(FONT_SIZE_SMALL)
+                    .style(styles::text::secondary),
+            );
+        }
+        column
+    }
+
+    /// The dialog shown over the whole window, if any.
+    pub fn modal(&self) -> Option<Element<'_, Message>> {
+        if let Some(manager) = &self.contexts {
+            return Some(manager.view().map(Message::Contexts));
+        }
+        if self.show_modal {
+            return Some(self.composer.view().map(Message::Composer));
+        }
+        None
+    }
+
+    /// Leave the previous context, its playbooks and live sync are gone.
+    fn leave_context(&mut self) {
+        self.selected_playbook = None;
+        self.playbooks = vec![];
+        self.supervisor.stop_all();
+        self.offered = false;
+    }
+
     fn omnibox(&self) -> Element<'_, Message> {
         Row::new()
-            .push(TextInput::new("Search", &self.query).on_input(Message::TextInputChanged))
+            .push(
+                TextInput::new("Search", &self.query)
+                    .on_input(Message::TextInputChanged)
+                    .on_submit(Message::SearchSubmitted),
+            )
             .push(self.button())
             .spacing(4)
             .into()
     }
 
     fn button(&self) -> Element<'_, Message> {
-        let underlay = Button::new(bootstrap::plus().size(ICON_FONT_SIZE_TINY).width(Length::Fixed(20.0)))
-            .on_press(Message::CreateButtonPressed);
+        Button::new(bootstrap::plus().size(ICON_FONT_SIZE_TINY).width(Length::Fixed(20.0)))
+            .on_press(Message::CreateButtonPressed)
+            .into()
+    }
+}
 
-        let _overlay = if self.show_modal {
-            Some(self.composer.view().map(Message::Composer))
-        } else {
-            None
-        };
+fn switcher(ctx: &Context, status: &ConnectionStatus) -> ContextSwitcher {
+    let config = ctx.configuration();
+    let context = config.context.as_ref().unwrap();
+    let (name, cluster) = context.current().unwrap_or_default();
+    ContextSwitcher::new(name, cluster.title, context.clusters().clone(), status.clone())
+}
 
-        underlay.into()
+fn notice_bar(notice: &str) -> Element<'_, Message> {
+    Row::new()
+        .push(
+            Text::new(notice)
+                .size(FONT_SIZE_SMALLER)
+                .style(styles::text::secondary)
+                .width(Length::Fill),
+        )
+        .push(
+            Button::new(bootstrap::x().size(ICON_FONT_SIZE_TINY))
+                .style(styles::button::text)
+                .on_press(Message::NoticeDismissed),
+        )
+        .align_y(Alignment::Center)
+        .spacing(SPACING_SMALL)
+        .into()
+}
 
-        // Modal::new(underlay, overlay)
-        //     .backdrop(Message::CloseComposeModal)
-        //     .on_esc(Message::CloseComposeModal)
-        //     .into()
-    }
+fn upload_progress(progress: &Progress) -> Element<'_, Message> {
+    Column::new()
+        .push(Text::new(format!("Uploading {}...", progress.actor)).size(FONT_SIZE_SMALL))
+        .push(ProgressBar::new(0.0..=1.0, progress.ratio()).girth(4))
+        .push(
+            Text::new(progress.to_string())
+                .size(FONT_SIZE_SMALLER)
+                .style(styles::text::secondary),
+        )
+        .spacing(SPACING_SMALL)
+        .into()
 }
 
-fn playbook_item(playbook: &PlaybookSpec, active: bool) -> Element<'_, Message> {
+fn listing_header(title: String, status: &ConnectionStatus) -> Element<'_, Message> {
+    let style = match status {
+        ConnectionStatus::Connected(_) => styles::text::success,
+        ConnectionStatus::Disconnected { .. } => styles::text::danger,
+        _ => styles::text::primary,
+    };
+    Row::new()
+        .push(Text::new("•").size(20).line_height(1.0).style(style))
+        .push(Text::new(title).size(FONT_SIZE_SMALL).width(Length::Fill))
+        .push(
+            Text::new(status.to_string())
+                .size(FONT_SIZE_SMALLER)
+                .style(styles::text::secondary),
+        )
+        .align_y(Alignment::Center)
+        .spacing(SPACING_SMALL)
+        .into()
+}
+
+fn playbook_item(hit: Hit<'_>, active: bool, focused: bool) -> Element<'_, Message> {
     let icon = bootstrap::r#box().size(ICON_FONT_SIZE_SIDEBAR);
 
+    let mut details = Column::new().push(highlighted(&hit.playbook.title, &hit.title, FONT_SIZE_STANDARD));
+    if let Some((text, indices)) = &hit.other {
+        details = details.push(highlighted(text, indices, FONT_SIZE_SMALLER));
+    }
     let content = Row::new()
         .push(icon)
-        .push(Text::new(&playbook.title))
+        .push(details)
         .align_y(Alignment::Center)
         .spacing(8);
 
     Button::new(content)
         .style(if active {
             styles::button::primary
+        } else if focused {
+            styles::button::secondary
         } else {
             styles::button::text
         })
         .width(Length::Fill)
-        .on_press(Message::PlaybookSelected(Some(Ok(playbook.clone()))))
+        .on_press(hit.message())
         .into()
 }
+
+/// The text with the chars at the given indices in bold.
+fn highlighted<'a>(text: &str, indices: &[usize], size: f32) -> Element<'a, Message> {
+    let bold = Font {
+        weight: Weight::Bold,
+        ..Font::DEFAULT
+    };
+
+    let mut row = Row::new();
+    let mut segment = String::new();
+    let mut matched = false;
+    for (index, c) in text.chars().enumerate() {
+        let is_match = indices.contains(&index);
+        if is_match != matched && !segment.is_empty() {
+            let font = if matched { bold } else { Font::DEFAULT };
+            row = row.push(Text::new(std::mem::take(&mut segment)).size(size).font(font));
+        }
+        matched = is_match;
+        segment.push(c);
+    }
+    if !segment.is_empty() {
+        let font = if matched { bold } else { Font::DEFAULT };
+        row = row.push(Text::new(segment).size(size).font(font));
+    }
+    row.into()
+}
+
+/// A playbook matching the search, and where it matched.
+struct Hit<'a> {
+    /// The context of the playbook, when it's not the current one.
+    context: Option<&'a String>,
+    playbook: &'a PlaybookSpec,
+    score: i64,
+    /// The char indices of the title matched.
+    title: Vec<usize>,
+    /// Another field matched better than the title, with its indices.
+    other: Option<(String, Vec<usize>)>,
+}
+
+impl<'a> Hit<'a> {
+    /// Match the playbook by its title, description, id and characters,
+    /// every playbook matches an empty query.
+    fn new(query: &str, context: Option<&'a String>, playbook: &'a PlaybookSpec) -> Option<Self> {
+        let mut hit = Hit {
+            context,
+            playbook,
+            score: 0,
+            title: vec![],
+            other: None,
+        };
+        if query.trim().is_empty() {
+            return Some(hit);
+        }
+
+        let title = fuzzy_match(query, &playbook.title).map(|(score, indices)| (score + TITLE_BONUS, indices));
+        let mut fields = vec![playbook.id.clone()];
+        fields.extend(playbook.description.clone());
+        fields.extend(playbook.characters.iter().flatten().map(|c| c.meta.name.clone()));
+        let other = fields
+            .into_iter()
+            .filter_map(|text| fuzzy_match(query, &text).map(|(score, indices)| (score, text, indices)))
+            .max_by_key(|(score, ..)| *score);
+
+        match (title, other) {
+            (Some((score, indices)), other) if other.as_ref().is_none_or(|(s, ..)| score >= *s) => {
+                hit.score = score;
+                hit.title = indices;
+            }
+            (_, Some((score, text, indices))) => {
+                hit.score = score;
+                hit.other = Some((text, indices));
+            }
+            _ => return None,
+        }
+        Some(hit)
+    }
+
+    fn message(&self) -> Message {
+        match self.context {
+            Some(context) => Message::ContextPlaybookSelected(context.clone(), self.playbook.clone()),
+            None => Message::PlaybookSelected(Some(Ok(self.playbook.clone()))),
+        }
+    }
+}
+
+/// Navigate the results with the keyboard, when nothing else took the keys.
+fn navigate(event: keyboard::Event) -> Option<Message> {
+    let keyboard::Event::KeyPressed { key, .. } = event else {
+        return None;
+    };
+    match key {
+        Key::Named(Named::ArrowDown) => Some(Message::CursorMoved(1)),
+        Key::Named(Named::ArrowUp) => Some(Message::CursorMoved(-1)),
+        Key::Named(Named::Enter) => Some(Message::SearchSubmitted),
+        Key::Named(Named::Escape) => Some(Message::SearchCleared),
+        _ => None,
+    }
+}
+
+/// Follow the progress published by the reporter of the uploads.
+struct Uploads(tokio::sync::watch::Receiver<Option<Progress>>);
+
+impl subscription::Recipe for Uploads {
+    type Output = Message;
+
+    fn hash(&self, state: &mut Hasher) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _: subscription::EventStream) -> BoxStream<Self::Output> {
+        futures::stream::unfold(self.0, |mut rx| async move {
+            rx.changed().await.ok()?;
+            let progress = rx.borrow_and_update().clone();
+            Some((Message::UploadProgressed(progress), rx))
+        })
+        .boxed()
+    }
+}
+
+/// Follow the changes of the amp configuration file.
+struct ConfigurationFile;
+
+impl subscription::Recipe for ConfigurationFile {
+    type Output = Message;
+
+    fn hash(&self, state: &mut Hasher) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _: subscription::EventStream) -> BoxStream<Self::Output> {
+        let changes = Configuration::path()
+            .map_err(|e| Errors::InvalidConfigPath(e.to_string()))
+            .and_then(config_watcher::watch);
+        match changes {
+            Ok(changes) => changes.map(|_| Message::ConfigurationChanged).boxed(),
+            Err(err) => {
+                error!("Failed to watch the configuration: {}", err);
+                futures::stream::pending().boxed()
+            }
+        }
+    }
+}
+
+/// Monitor the connection to the cluster of the current context, it's
+/// restarted whenever the cluster changes.
+struct Connection(Context);
+
+impl subscription::Recipe for Connection {
+    type Output = Message;
+
+    fn hash(&self, state: &mut Hasher) {
+        std::any::TypeId::of::<Self>().hash(state);
+        hash_cluster(&self.0, state);
+    }
+
+    fn stream(self: Box<Self>, _: subscription::EventStream) -> BoxStream<Self::Output> {
+        connection_status::monitor(self.0.client())
+            .map(Message::ConnectionChanged)
+            .boxed()
+    }
+}
+
+/// Watch the playbooks of the cluster of the current context, it's
+/// restarted whenever the cluster changes.
+struct Playbooks(Context);
+
+impl subscription::Recipe for Playbooks {
+    type Output = Message;
+
+    fn hash(&self, state: &mut Hasher) {
+        std::any::TypeId::of::<Self>().hash(state);
+        hash_cluster(&self.0, state);
+    }
+
+    fn stream(self: Box<Self>, _: subscription::EventStream) -> BoxStream<Self::Output> {
+        playbook_watcher::watch(self.0.client())
+            .map(Message::PlaybookChanged)
+            .boxed()
+    }
+}
+
+/// Watch the playbooks of another listed cluster.
+struct ListingPlaybooks(Context);
+
+impl subscription::Recipe for ListingPlaybooks {
+    type Output = Message;
+
+    fn hash(&self, state: &mut Hasher) {
+        std::any::TypeId::of::<Self>().hash(state);
+        hash_cluster(&self.0, state);
+    }
+
+    fn stream(self: Box<Self>, _: subscription::EventStream) -> BoxStream<Self::Output> {
+        let name = self.0.name();
+        playbook_watcher::watch(self.0.client())
+            .map(move |event| Message::ListingChanged(name.clone(), event))
+            .boxed()
+    }
+}
+
+/// Monitor the connection to another listed cluster.
+struct ListingConnection(Context);
+
+impl subscription::Recipe for ListingConnection {
+    type Output = Message;
+
+    fn hash(&self, state: &mut Hasher) {
+        std::any::TypeId::of::<Self>().hash(state);
+        hash_cluster(&self.0, state);
+    }
+
+    fn stream(self: Box<Self>, _: subscription::EventStream) -> BoxStream<Self::Output> {
+        let name = self.0.name();
+        connection_status::monitor(self.0.client())
+            .map(move |status| Message::ListingStatusChanged(name.clone(), status))
+            .boxed()
+    }
+}
+
+/// Hash the cluster of the context, for the subscriptions to it.
+fn hash_cluster(ctx: &Context, state: &mut Hasher) {
+    (ctx.name(), ctx.endpoint()).hash(state);
+}
@@ -0,0 +1,130 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::config::Cluster;
+
+use crate::styles::constants::*;
+use crate::widgets::{Column, Element, Text, TextInput};
+
+/// ClusterForm edits the name and the settings of a cluster, shared by the
+/// onboarding and the context management.
+#[derive(Clone, Debug, Default)]
+pub struct ClusterForm {
+    name: String,
+    title: String,
+    server: String,
+    token: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    NameChanged(String),
+    TitleChanged(String),
+    ServerChanged(String),
+    TokenChanged(String),
+}
+
+impl ClusterForm {
+    /// Fill the form with an existing cluster.
+    pub fn new(name: String, cluster: &Cluster) -> Self {
+        Self {
+            name,
+            title: cluster.title.clone(),
+            server: cluster.server.clone(),
+            token: cluster.token.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::NameChanged(name) => self.name = name,
+            Message::TitleChanged(title) => self.title = title,
+            Message::ServerChanged(server) => self.server = server,
+            Message::TokenChanged(token) => self.token = token,
+        }
+    }
+
+    /// Whether the required fields are filled.
+    pub fn is_complete(&self) -> bool {
+        !self.name.trim().is_empty() && !self.server.trim().is_empty()
+    }
+
+    /// The name and the cluster of the form, trimmed. The title defaults to
+    /// the name, and an empty token means none.
+    pub fn cluster(&self) -> (String, Cluster) {
+        let name = self.name.trim().to_string();
+        let title = match self.title.trim() {
+            "" => name.clone(),
+            title => title.to_string(),
+        };
+        let token = Some(self.token.trim().to_string()).filter(|token| !token.is_empty());
+        let cluster = Cluster {
+            title,
+            server: self.server.trim().trim_end_matches('/').to_string(),
+            token,
+        };
+        (name, cluster)
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        Column::new()
+            .push(field(
+                "Name",
+                "The name of the context, e.g. production",
+                &self.name,
+                Message::NameChanged,
+                false,
+            ))
+            .push(field(
+                "Title",
+                "The display name, same as the name if empty",
+                &self.title,
+                Message::TitleChanged,
+                false,
+            ))
+            .push(field(
+                "Server",
+                "The server URL, e.g. https://cloud.amphitheatre.app",
+                &self.server,
+                Message::ServerChanged,
+                false,
+            ))
+            .push(field(
+                "Token",
+                "The access token, if the server requires one",
+                &self.token,
+                Message::TokenChanged,
+                true,
+            ))
+            .spacing(SPACING_LARGE)
+            .into()
+    }
+}
+
+fn field<'a>(
+    label: &'a str,
+    placeholder: &'a str,
+    value: &'a str,
+    on_input: impl Fn(String) -> Message + 'a,
+    secure: bool,
+) -> Element<'a, Message> {
+    Column::with_children(vec![
+        Text::new(label).into(),
+        TextInput::new(placeholder, value)
+            .on_input(on_input)
+            .secure(secure)
+            .into(),
+    ])
+    .into()
+}
@@ -12,6 +12,8 @@
 use std::collections::HashMap;
 
 use amp_common::config::Cluster;
+use iced::widget::tooltip;
+use iced::widget::tooltip::Position;
 use iced::{Alignment, Length};
 use iced_fonts::bootstrap;
 use tracing::debug;
@@ -33,11 +35,14 @@ pub struct ContextSwitcher {
 #[derive(Debug, Clone)]
 pub enum Message {
     ItemPressed(String),
+    ManagePressed,
 }
 
 pub enum Action {
     None,
     Switch(String),
+    /// Open the context management.
+    Manage,
 }
 
 impl ContextSwitcher {
@@ -50,6 +55,11 @@ impl ContextSwitcher {
         }
     }
 
+    /// Show the latest connection status of the current context.
+    pub fn set_status(&mut self, status: ConnectionStatus) {
+        self.status = status;
+    }
+
     pub fn update(&mut self, message: Message) -> Action {
         match message {
             Message::ItemPressed(name) => {
@@ -60,20 +70,32 @@ impl ContextSwitcher {
                 }
                 Action::Switch(name)
             }
+            Message::ManagePressed => Action::Manage,
         }
     }
 
     pub fn view(&self) -> Element<'_, Message> {
         let style = match self.status {
-            ConnectionStatus::Connecting => styles::text::primary,
-            ConnectionStatus::Connected => styles::text::success,
-            ConnectionStatus::Disconnected => styles::text::danger,
+            ConnectionStatus::Connecting | ConnectionStatus::Reconnecting(_) => styles::text::primary,
+            ConnectionStatus::Connected(_) => styles::text::success,
+            ConnectionStatus::Disconnected { .. } => styles::text::danger,
         };
         let text = self.status.to_string();
         let state = Row::new()
             .push(Text::new("•").size(20).line_height(1.0).style(style))
             .push(Text::new(text).size(14).style(styles::text::secondary))
             .align_y(Alignment::Center);
+        let state: Element<'_, Message> = match self.status.error() {
+            Some(error) => tooltip(
+                state,
+                Container::new(Text::new(error).size(14))
+                    .padding(8)
+                    .style(styles::container::toolbar),
+                Position::Bottom,
+            )
+            .into(),
+            None => state.into(),
+        };
 
         let title = Column::new()
             .push(Text::new(&self.title))
@@ -90,9 +112,10 @@ impl ContextSwitcher {
             .align_y(Alignment::Center)
             .width(Length::Fill);
 
-        let items = self
-            .clusters
-            .iter()
+        let mut clusters: Vec<_> = self.clusters.iter().collect();
+        clusters.sort_by(|a, b| a.0.cmp(b.0));
+        let mut items: Vec<_> = clusters
+            .into_iter()
             .map(|(name, cluster)| {
                 Item::new(
                     Button::new(Text::new(&cluster.title))
@@ -102,6 +125,12 @@ impl ContextSwitcher {
                 )
             })
             .collect();
+        items.push(Item::new(
+            Button::new(Text::new("Manage contexts...").style(styles::text::secondary))
+                .style(styles::button::text)
+                .width(Length::Fill)
+                .on_press(Message::ManagePressed),
+        ));
 
         let root_menu_items = Item::with_menu(header, Menu::new(items).max_width(190.0));
         let content = MenuBar::new(vec![root_menu_items]).width(Length::Fill);
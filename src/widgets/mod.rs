@@ -13,8 +13,10 @@
 // limitations under the License.
 
 pub mod character_switcher;
+pub mod cluster_form;
 pub mod context_switcher;
 pub mod empty;
+pub mod modal;
 pub mod tabs;
 
 use crate::styles::Theme;
@@ -35,6 +37,8 @@ pub mod menu {
     pub type Item<'a, Message> = iced_aw::menu::Item<'a, Message, Theme, Renderer>;
 }
 
+pub type ProgressBar<'a> = iced::widget::ProgressBar<'a, Theme>;
+pub type Radio<'a, Message> = iced::widget::Radio<'a, Message, Theme>;
 pub type Row<'a, Message> = iced::widget::Row<'a, Message, Theme>;
 pub type Rule<'a> = iced::widget::Rule<'a, Theme>;
 pub use iced::widget::rule;